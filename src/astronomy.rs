@@ -0,0 +1,199 @@
+//! Positions of the sun and the phase of the moon.
+//!
+//! The sun follows the NOAA solar calculator, which is accurate to well under a degree for the years the
+//! app will see. The moon phase uses the low accuracy formulas from Jean Meeus, "Astronomical Algorithms",
+//! chapter 48. Both only need the coordinates and the time, so they work offline.
+
+/// The Julian day of the unix epoch.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+
+/// The Julian day of the J2000 epoch.
+const J2000: f64 = 2_451_545.0;
+
+/// Where the sun is in the sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunPosition {
+    /// The compass direction of the sun in degrees, clockwise from north.
+    pub azimuth: f64,
+    /// The height of the sun in degrees above the horizon, negative below it.
+    pub elevation: f64,
+}
+
+/// The phase of the moon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoonPhase {
+    /// How far through the lunar month the moon is, from 0 at new moon over 0.5 at full moon back to 1.
+    pub phase: f64,
+    /// The lit part of the moon's disk, from 0 to 1.
+    pub illumination: f64,
+}
+
+impl MoonPhase {
+    /// The function that returns whether the lit part of the moon is growing.
+    pub fn is_waxing(&self) -> bool {
+        self.phase < 0.5
+    }
+}
+
+/// The function that returns the Julian day of the unix timestamp.
+pub fn julian_day(unix: i64) -> f64 {
+    unix as f64 / 86_400.0 + UNIX_EPOCH_JULIAN_DAY
+}
+
+/// The function that returns the Julian centuries since J2000 of the unix timestamp.
+fn julian_century(unix: i64) -> f64 {
+    (julian_day(unix) - J2000) / 36_525.0
+}
+
+/// The function that calculates where the sun is for the latitude and longitude in degrees at the unix timestamp.
+/// Longitudes are positive to the east.
+pub fn sun_position(lat: f64, lon: f64, unix: i64) -> SunPosition {
+    let t = julian_century(unix);
+
+    let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+    let m = mean_anomaly.to_radians();
+
+    let center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+        + (3.0 * m).sin() * 0.000289;
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude =
+        (mean_longitude + center - 0.00569 - 0.00478 * omega.sin()).to_radians();
+
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+    let declination = (obliquity.sin() * apparent_longitude.sin()).asin();
+
+    // The equation of time in minutes
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l0 = mean_longitude.to_radians();
+    let equation_of_time = 4.0
+        * (y * (2.0 * l0).sin() - 2.0 * eccentricity * m.sin()
+            + 4.0 * eccentricity * y * m.sin() * (2.0 * l0).cos()
+            - 0.5 * y * y * (4.0 * l0).sin()
+            - 1.25 * eccentricity * eccentricity * (2.0 * m).sin())
+        .to_degrees();
+
+    let minutes = unix.rem_euclid(86_400) as f64 / 60.0;
+    let true_solar_time = (minutes + equation_of_time + 4.0 * lon).rem_euclid(1440.0);
+    let hour_angle = (true_solar_time / 4.0 - 180.0).to_radians();
+
+    let lat = lat.to_radians();
+    let cos_zenith = (lat.sin() * declination.sin()
+        + lat.cos() * declination.cos() * hour_angle.cos())
+    .clamp(-1.0, 1.0);
+    let zenith = cos_zenith.acos();
+
+    let sin_zenith = zenith.sin();
+    let azimuth = if lat.cos() * sin_zenith == 0.0 {
+        180.0
+    } else {
+        let cos_azimuth = ((lat.sin() * cos_zenith - declination.sin()) / (lat.cos() * sin_zenith))
+            .clamp(-1.0, 1.0);
+        let angle = cos_azimuth.acos().to_degrees();
+        if hour_angle > 0.0 {
+            (angle + 180.0).rem_euclid(360.0)
+        } else {
+            (540.0 - angle).rem_euclid(360.0)
+        }
+    };
+
+    SunPosition {
+        azimuth,
+        elevation: 90.0 - zenith.to_degrees(),
+    }
+}
+
+/// The function that calculates the phase of the moon at the unix timestamp.
+pub fn moon_phase(unix: i64) -> MoonPhase {
+    let t = julian_century(unix);
+
+    // The mean elongation of the moon and the mean anomalies of the sun and the moon
+    let d = (297.8501921 + 445267.1114034 * t - 0.0018819 * t * t).rem_euclid(360.0);
+    let m = (357.5291092 + 35999.0502909 * t - 0.0001536 * t * t).to_radians();
+    let m_moon = (134.9633964 + 477198.8675055 * t + 0.0087414 * t * t).to_radians();
+    let d_rad = d.to_radians();
+
+    let phase_angle = 180.0 - d - 6.289 * m_moon.sin() + 2.100 * m.sin()
+        - 1.274 * (2.0 * d_rad - m_moon).sin()
+        - 0.658 * (2.0 * d_rad).sin()
+        - 0.214 * (2.0 * m_moon).sin()
+        - 0.110 * d_rad.sin();
+    let phase_angle = phase_angle.rem_euclid(360.0);
+
+    // The phase angle is 180 degrees at new moon and falls to 0 at full moon while waxing
+    let phase = ((180.0 - phase_angle) / 360.0).rem_euclid(1.0);
+
+    MoonPhase {
+        phase,
+        illumination: (1.0 + phase_angle.to_radians().cos()) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREENWICH: (f64, f64) = (51.4779, 0.0);
+
+    #[test]
+    fn test_julian_day() {
+        assert_eq!(julian_day(0), 2_440_587.5);
+        // 2000-01-01 12:00 UTC
+        assert_eq!(julian_day(946_728_000), J2000);
+    }
+
+    #[test]
+    fn test_sun_at_solstices() {
+        // 2023-06-21 12:00 UTC, the sun is about 90 - 51.48 + 23.44 degrees high in the south
+        let summer = sun_position(GREENWICH.0, GREENWICH.1, 1687348800);
+        assert!((summer.elevation - 61.96).abs() < 0.3, "{:?}", summer);
+        assert!((summer.azimuth - 180.0).abs() < 3.0, "{:?}", summer);
+
+        // 2023-12-22 12:00 UTC
+        let winter = sun_position(GREENWICH.0, GREENWICH.1, 1703246400);
+        assert!((winter.elevation - 15.08).abs() < 0.3, "{:?}", winter);
+    }
+
+    #[test]
+    fn test_sun_in_the_morning() {
+        // 2023-06-21 06:00 UTC in Greenwich, with a declination of 23.44 and an hour angle of -90.45 degrees
+        // the sun is 17.9 degrees high at an azimuth of 74.6 degrees
+        let morning = sun_position(GREENWICH.0, GREENWICH.1, 1687327200);
+        assert!((morning.elevation - 17.9).abs() < 0.3, "{:?}", morning);
+        assert!((morning.azimuth - 74.6).abs() < 0.5, "{:?}", morning);
+
+        // The sun is below the horizon on the other side of the world
+        let night = sun_position(GREENWICH.0, 180.0, 1687348800);
+        assert!(night.elevation < 0.0);
+    }
+
+    #[test]
+    fn test_moon_phases() {
+        // New moon on 2024-01-11 11:57 UTC
+        let new = moon_phase(1704974220);
+        assert!(new.illumination < 0.01, "{:?}", new);
+
+        // First quarter on 2024-01-18 03:53 UTC
+        let first_quarter = moon_phase(1705549980);
+        assert!(
+            (first_quarter.illumination - 0.5).abs() < 0.03,
+            "{:?}",
+            first_quarter
+        );
+        assert!(first_quarter.is_waxing());
+        assert!((first_quarter.phase - 0.25).abs() < 0.02);
+
+        // Full moon on 2024-01-25 17:54 UTC
+        let full = moon_phase(1706205240);
+        assert!(full.illumination > 0.99, "{:?}", full);
+
+        // Last quarter on 2024-02-02 23:18 UTC
+        let last_quarter = moon_phase(1706915880);
+        assert!(!last_quarter.is_waxing());
+        assert!((last_quarter.phase - 0.75).abs() < 0.02);
+    }
+}
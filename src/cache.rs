@@ -0,0 +1,407 @@
+//! The on-disk cache of weather reports.
+//!
+//! Every report and forecast fetched for the window, the terminal or a render is saved under the cache
+//! directory with the time it was fetched, keyed by the provider and the place. The weather is always
+//! fetched in metric units and only converted when it is shown, so the units are not part of the key. A
+//! report younger than the time to live is used instead of asking the API again, since OpenWeatherMap only
+//! updates about every ten minutes. When the API cannot be reached, the last cached report is shown
+//! instead, marked as stale.
+//!
+//! The cache keeps the parsed reports rather than the raw responses of the APIs, so every provider shares
+//! one entry format instead of each parsing its own bodies again. The entries are saved with the version
+//! of their format, and entries of another version are ignored, so a change to the report types fetches
+//! the weather again rather than reading an old entry wrongly.
+
+use crate::error::WeatherError;
+use crate::provider::{Location, Place, WeatherProvider};
+use crate::report::{Forecast, WeatherReport};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The seconds a cached report is used before the weather is fetched again.
+pub const DEFAULT_TTL: u64 = 600;
+
+/// The version of the format of the cache entries, raised whenever the cached types change.
+pub const ENTRY_VERSION: u32 = 1;
+
+/// A cached report or forecast with the time it was fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry<T> {
+    /// The version of the format the entry was saved in.
+    pub version: u32,
+    /// The time the report was fetched, as a unix timestamp.
+    pub fetched_at: i64,
+    pub value: T,
+}
+
+/// The directory the reports are cached in, with the time they are used for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    /// The directory of the cache, or `None` to cache nothing.
+    dir: Option<PathBuf>,
+    /// The seconds a cached report is used before the weather is fetched again.
+    ttl: u64,
+}
+
+impl Cache {
+    /// The function that returns a cache in the directory.
+    pub fn new(dir: Option<PathBuf>, ttl: u64) -> Cache {
+        Cache { dir, ttl }
+    }
+
+    /// The function that returns the cache in the user's cache directory: `$XDG_CACHE_HOME` or else
+    /// `~/.cache`. Without either nothing is cached.
+    pub fn in_user_dir(ttl: u64) -> Cache {
+        let dir = env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join("weather_visualizer"));
+        Cache::new(dir, ttl)
+    }
+
+    /// The function that returns the file the report with the key is cached in.
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(format!("{}.json", key)))
+    }
+
+    /// The function that returns the cached entry with the key, however old it is.
+    /// Entries saved in another version of the format are not returned.
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Option<Entry<T>> {
+        let text = fs::read_to_string(self.path(key)?).ok()?;
+        let entry: Entry<T> = serde_json::from_str(&text).ok()?;
+        (entry.version == ENTRY_VERSION).then_some(entry)
+    }
+
+    /// The function that returns whether an entry is still fresh at `now`.
+    pub fn is_fresh<T>(&self, entry: &Entry<T>, now: i64) -> bool {
+        (now - entry.fetched_at) < self.ttl as i64
+    }
+
+    /// The function that saves the report or forecast fetched at `now` under the key.
+    /// The cache only saves requests, so a value that cannot be saved is not an error.
+    pub fn store<T: Serialize>(&self, key: &str, value: &T, now: i64) {
+        let (Some(dir), Some(path)) = (&self.dir, self.path(key)) else {
+            return;
+        };
+        let entry = Entry {
+            version: ENTRY_VERSION,
+            fetched_at: now,
+            value,
+        };
+        if let Ok(text) = serde_json::to_string(&entry) {
+            fs::create_dir_all(dir).ok();
+            fs::write(path, text).ok();
+        }
+    }
+}
+
+/// The function that returns the key of the report of the provider for the place, such as
+/// "openweathermap_london".
+pub fn cache_key(provider: &str, location: &Location) -> String {
+    let place = match location {
+        Location::City(city) => city.clone(),
+        Location::Coords { lat, lon } => format!("{:.4},{:.4}", lat, lon),
+    };
+    [provider, &place]
+        .iter()
+        .map(|part| {
+            part.trim()
+                .to_lowercase()
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() || ".,-".contains(c) {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// The function that returns whether the error means the API could not be reached or could not answer,
+/// so the last cached report should be shown.
+fn is_offline(error: &WeatherError) -> bool {
+    match error {
+        WeatherError::Transport(_) | WeatherError::RateLimited => true,
+        WeatherError::Status(status) => *status >= 500,
+        _ => false,
+    }
+}
+
+/// The function that returns the current time as a unix timestamp.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// A weather provider whose current weather and forecasts are cached on disk.
+pub struct CachedProvider {
+    provider: Box<dyn WeatherProvider>,
+    cache: Cache,
+}
+
+impl CachedProvider {
+    pub fn new(provider: Box<dyn WeatherProvider>, cache: Cache) -> CachedProvider {
+        CachedProvider { provider, cache }
+    }
+
+    /// The function that returns the fresh cached value with the key when `reuse` is set, or else fetches
+    /// and saves it. When the API cannot be reached, the stale cached value is returned with the time it was
+    /// fetched.
+    fn cached<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &str,
+        reuse: bool,
+        fetch: impl FnOnce() -> Result<T, WeatherError>,
+    ) -> Result<(T, Option<i64>), WeatherError> {
+        let now = now();
+        let cached = self.cache.load::<T>(key);
+        match cached {
+            Some(entry) if reuse && self.cache.is_fresh(&entry, now) => Ok((entry.value, None)),
+            cached => match (fetch(), cached) {
+                (Ok(value), _) => {
+                    self.cache.store(key, &value, now);
+                    Ok((value, None))
+                }
+                (Err(e), Some(entry)) if is_offline(&e) => {
+                    Ok((entry.value, Some(entry.fetched_at)))
+                }
+                (Err(e), _) => Err(e),
+            },
+        }
+    }
+}
+
+impl WeatherProvider for CachedProvider {
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let key = cache_key(self.provider.name(), location);
+        let (report, stale_since) = self.cached(&key, true, || self.provider.current(location))?;
+        Ok(WeatherReport {
+            stale_since,
+            ..report
+        })
+    }
+
+    /// The function that fetches the current weather however fresh the cached report is, and saves it.
+    /// When the API cannot be reached, the cached report is still shown as stale.
+    fn refresh(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let key = cache_key(self.provider.name(), location);
+        let (report, stale_since) = self.cached(&key, false, || self.provider.current(location))?;
+        Ok(WeatherReport {
+            stale_since,
+            ..report
+        })
+    }
+
+    fn forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        let key = format!("{}_forecast", cache_key(self.provider.name(), location));
+        let (forecast, _) = self.cached(&key, true, || self.provider.forecast(location))?;
+        Ok(forecast)
+    }
+
+    /// The function that fetches the forecast however fresh the cached forecast is, and saves it.
+    fn refresh_forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        let key = format!("{}_forecast", cache_key(self.provider.name(), location));
+        let (forecast, _) = self.cached(&key, false, || self.provider.forecast(location))?;
+        Ok(forecast)
+    }
+
+    fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError> {
+        self.provider.geocode(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A provider that counts its requests and answers with the report, or fails when it is offline.
+    struct Counting {
+        requests: Arc<AtomicUsize>,
+        offline: bool,
+    }
+
+    impl WeatherProvider for Counting {
+        fn name(&self) -> &'static str {
+            "Counting"
+        }
+
+        fn current(&self, _location: &Location) -> Result<WeatherReport, WeatherError> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            if self.offline {
+                return Err(WeatherError::Status(503));
+            }
+            Ok(serde_json::from_str(r#"{"main": {"temp": 9.5}, "name": "Oslo"}"#).unwrap())
+        }
+
+        fn forecast(&self, _location: &Location) -> Result<Forecast, WeatherError> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            if self.offline {
+                return Err(WeatherError::Status(503));
+            }
+            Ok(serde_json::from_str(r#"{"list": [], "city": {"name": "Oslo"}}"#).unwrap())
+        }
+
+        fn geocode(&self, _query: &str) -> Result<Vec<Place>, WeatherError> {
+            Err(WeatherError::Unsupported("geocoding"))
+        }
+    }
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "weather_visualizer_{}_{}",
+            name,
+            std::process::id()
+        ));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn provider(dir: &Path, ttl: u64, offline: bool) -> (CachedProvider, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counting = Counting {
+            requests: requests.clone(),
+            offline,
+        };
+        let cache = Cache::new(Some(dir.to_path_buf()), ttl);
+        (CachedProvider::new(Box::new(counting), cache), requests)
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(
+            cache_key("OpenWeatherMap", &Location::City("New York".into())),
+            "openweathermap_new-york"
+        );
+        assert_eq!(
+            cache_key(
+                "Open-Meteo",
+                &Location::Coords {
+                    lat: 40.4165,
+                    lon: -3.7026
+                }
+            ),
+            "open-meteo_40.4165,-3.7026"
+        );
+    }
+
+    #[test]
+    fn test_fresh_reports_are_not_fetched_again() {
+        let dir = cache_dir("fresh");
+        let oslo = Location::City("Oslo".into());
+
+        let (cached, requests) = provider(&dir, DEFAULT_TTL, false);
+        assert_eq!(cached.current(&oslo).unwrap().name, "Oslo");
+        assert_eq!(cached.current(&oslo).unwrap().name, "Oslo");
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        // Without a time to live every report is fetched
+        let (cached, requests) = provider(&dir, 0, false);
+        cached.current(&oslo).unwrap();
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_offline_shows_the_stale_report() {
+        let dir = cache_dir("offline");
+        let oslo = Location::City("Oslo".into());
+
+        let (cached, _) = provider(&dir, 0, true);
+        assert!(matches!(
+            cached.current(&oslo),
+            Err(WeatherError::Status(503))
+        ));
+
+        let (online, _) = provider(&dir, 0, false);
+        let report = online.current(&oslo).unwrap();
+        assert_eq!(report.stale_since, None);
+
+        let (cached, _) = provider(&dir, 0, true);
+        let stale = cached.current(&oslo).unwrap();
+        assert_eq!(stale.name, "Oslo");
+        assert!(stale.stale_since.is_some());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_refresh_fetches_fresh_reports_again() {
+        let dir = cache_dir("refresh");
+        let oslo = Location::City("Oslo".into());
+
+        let (cached, requests) = provider(&dir, DEFAULT_TTL, false);
+        cached.current(&oslo).unwrap();
+        assert_eq!(cached.refresh(&oslo).unwrap().stale_since, None);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        let (offline, _) = provider(&dir, DEFAULT_TTL, true);
+        let stale = offline.refresh(&oslo).unwrap();
+        assert_eq!(stale.name, "Oslo");
+        assert!(stale.stale_since.is_some());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_forecasts_are_cached() {
+        let dir = cache_dir("forecast");
+        let oslo = Location::City("Oslo".into());
+
+        let (cached, requests) = provider(&dir, DEFAULT_TTL, false);
+        cached.current(&oslo).unwrap();
+        assert_eq!(cached.forecast(&oslo).unwrap().city.name, "Oslo");
+        assert_eq!(cached.forecast(&oslo).unwrap().city.name, "Oslo");
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        cached.refresh_forecast(&oslo).unwrap();
+        assert_eq!(requests.load(Ordering::Relaxed), 3);
+
+        let (offline, _) = provider(&dir, 0, true);
+        assert_eq!(offline.forecast(&oslo).unwrap().city.name, "Oslo");
+        assert_eq!(offline.refresh_forecast(&oslo).unwrap().city.name, "Oslo");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_other_versions_are_ignored() {
+        let dir = cache_dir("version");
+        let oslo = Location::City("Oslo".into());
+        let key = cache_key("Counting", &oslo);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.json", key)),
+            format!(
+                r#"{{"version": {}, "fetched_at": {}, "value": {{"main": {{"temp": 1.0}}, "name": "Old"}}}}"#,
+                ENTRY_VERSION + 1,
+                now()
+            ),
+        )
+        .unwrap();
+
+        let (cached, requests) = provider(&dir, DEFAULT_TTL, false);
+        assert_eq!(cached.current(&oslo).unwrap().name, "Oslo");
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,512 @@
+//! The command line of the app.
+//!
+//! Without arguments the app greets the user and asks for a city in the terminal. The subcommands run
+//! without any prompts, so the app can be started from scripts, desktop shortcuts and kiosks. The older
+//! `--render out.png --city London` form without a subcommand still works.
+
+use crate::cache;
+use crate::provider::{self, Location};
+use crate::render::Animation;
+use crate::units::Units;
+use crate::watch::{self, Watch};
+use std::env;
+use std::path::PathBuf;
+
+/// The help printed for `help`, `--help` and invalid arguments.
+pub const USAGE: &str = "\
+Usage:
+  weather_visualizer [--units <units>] [--provider <name>] [--seed <n>] [watch options]
+      Ask for a city in the terminal and show its weather in a window.
+  weather_visualizer show (--city <city> | --lat <lat> --lon <lon>) [options] [watch options]
+      Show the weather for the place in a window without any prompts.
+  weather_visualizer render <output> (--city <city> | --lat <lat> --lon <lon>) [options]
+      [--seconds <n>] [--fps <n>] [--size <width>x<height>]
+      Save the scene as a .png image, or as a .gif, .apng or .png sequence animation.
+  weather_visualizer forecast (--city <city> | --lat <lat> --lon <lon>) [options]
+      Print the 3-hour forecast for the place.
+
+Options:
+  --units <units>      metric, imperial or standard, or WEATHER_UNITS in .env (default metric)
+  --provider <name>    openweathermap, open-meteo, nws or metar, or WEATHER_PROVIDER in .env
+  --seed <n>           the seed of the scene's random numbers, or WEATHER_SEED in .env
+  --cache-ttl <secs>   how long a fetched report is reused, or WEATHER_CACHE_TTL in .env (default 600)
+
+Watch options, to keep the window up to date on always-on displays:
+  --watch <secs>       fetch the weather again every <secs> seconds, or WEATHER_WATCH in .env
+  --fade <secs>        how long the scene fades when the weather changes, or WEATHER_FADE (default 2)
+  --max-backoff <secs> the longest wait between attempts after failures, or WEATHER_MAX_BACKOFF (default 3600)";
+
+/// The options every command shares.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    /// The place from `--city` or `--lat` and `--lon`.
+    pub location: Option<Location>,
+    pub units: Option<Units>,
+    pub provider: Option<String>,
+    pub seed: Option<u64>,
+    /// The seconds a cached report is used before the weather is fetched again.
+    pub cache_ttl: Option<u64>,
+    /// The seconds between two refreshes of the window in watch mode.
+    pub watch: Option<f32>,
+    /// The seconds the scene fades when the weather changes in watch mode.
+    pub fade: Option<f32>,
+    /// The longest seconds between two attempts after failed refreshes in watch mode.
+    pub max_backoff: Option<f32>,
+}
+
+impl Options {
+    /// The function that returns the units from `--units`, or else from the `WEATHER_UNITS` environment
+    /// variable, or else metric units.
+    pub fn units(&self) -> Units {
+        self.units
+            .or_else(|| {
+                env::var("WEATHER_UNITS")
+                    .ok()
+                    .and_then(|name| Units::from_name(&name))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The function that returns the name of the provider from `--provider`, or else from the
+    /// `WEATHER_PROVIDER` environment variable. Without either, the provider is chosen automatically.
+    pub fn provider(&self) -> Option<&'static str> {
+        match &self.provider {
+            Some(name) => provider::canonical_name(name),
+            None => env::var("WEATHER_PROVIDER")
+                .ok()
+                .and_then(|name| provider::canonical_name(name.trim())),
+        }
+    }
+
+    /// The function that returns the seed from `--seed`, or else from the `WEATHER_SEED` environment
+    /// variable. Without either, every scene is seeded from its city and time.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed.or_else(|| {
+            env::var("WEATHER_SEED")
+                .ok()
+                .and_then(|seed| seed.trim().parse().ok())
+        })
+    }
+
+    /// The function that returns the time to live of cached reports from `--cache-ttl`, or else from the
+    /// `WEATHER_CACHE_TTL` environment variable, or else ten minutes.
+    pub fn cache_ttl(&self) -> u64 {
+        self.cache_ttl
+            .or_else(|| {
+                env::var("WEATHER_CACHE_TTL")
+                    .ok()
+                    .and_then(|ttl| ttl.trim().parse().ok())
+            })
+            .unwrap_or(cache::DEFAULT_TTL)
+    }
+
+    /// The function that returns the watch mode from `--watch`, `--fade` and `--max-backoff`, or else from
+    /// the `WEATHER_WATCH`, `WEATHER_FADE` and `WEATHER_MAX_BACKOFF` environment variables.
+    /// Without an interval the window never refreshes.
+    pub fn watch(&self) -> Option<Watch> {
+        let seconds = |value: Option<f32>, name: &str| {
+            value.or_else(|| {
+                env::var(name)
+                    .ok()
+                    .and_then(|seconds| seconds.trim().parse().ok())
+                    .filter(|seconds: &f32| *seconds >= 0.0)
+            })
+        };
+
+        Some(Watch {
+            interval: seconds(self.watch, "WEATHER_WATCH").filter(|interval| *interval > 0.0)?,
+            fade: seconds(self.fade, "WEATHER_FADE").unwrap_or(watch::DEFAULT_FADE),
+            max_backoff: seconds(self.max_backoff, "WEATHER_MAX_BACKOFF")
+                .unwrap_or(watch::DEFAULT_MAX_BACKOFF),
+        })
+    }
+}
+
+/// What to render without a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub options: Options,
+    pub output: PathBuf,
+    /// The length of the animation, or `None` for a still image unless the output is a GIF or APNG.
+    pub seconds: Option<f32>,
+    pub fps: u32,
+    pub size: [u32; 2],
+}
+
+/// What the app should do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Ask for a city in the terminal, the default without a subcommand.
+    Interactive(Options),
+    /// Show the weather for the place in a window.
+    Show(Options),
+    /// Save the scene for the place as an image or an animation.
+    Render(RenderOptions),
+    /// Print the forecast for the place.
+    Forecast(Options),
+    Help,
+}
+
+/// The function that returns the name of the provider the user gave in `source`, or else a message
+/// listing the providers to choose from.
+fn provider_name(name: &str, source: &str) -> Result<String, String> {
+    provider::canonical_name(name)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            format!(
+                "Invalid value '{}' for {}, choose one of {}.",
+                name,
+                source,
+                provider::NAMES.join("|")
+            )
+        })
+}
+
+/// The function that reads the command from the arguments, without the program name.
+/// The function returns a message for the user if the arguments are invalid.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let (subcommand, flags) = match args.first() {
+        Some(first) if !first.starts_with('-') => (Some(first.as_str()), &args[1..]),
+        _ => (None, args),
+    };
+    match subcommand {
+        None | Some("show" | "render" | "forecast") => {}
+        Some("help") => return Ok(Command::Help),
+        Some(other) => return Err(format!("Unknown command '{}'.", other)),
+    }
+
+    let defaults = Animation::default();
+    let mut options = Options::default();
+    let mut city = None;
+    let (mut lat, mut lon) = (None, None);
+    let mut output = None;
+    let mut seconds = None;
+    let mut fps = None;
+    let mut size = None;
+
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        if flag == "--help" || flag == "-h" {
+            return Ok(Command::Help);
+        }
+        if !flag.starts_with('-') {
+            if subcommand == Some("render") && output.is_none() {
+                output = Some(PathBuf::from(flag));
+                continue;
+            }
+            return Err(format!("Unexpected argument '{}'.", flag));
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing a value for {}.", flag))?;
+        let invalid = || format!("Invalid value '{}' for {}.", value, flag);
+        match flag.as_str() {
+            "--city" => city = Some(value.clone()),
+            "--lat" => {
+                lat = Some(
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|lat| lat.abs() <= 90.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--lon" => {
+                lon = Some(
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|lon| lon.abs() <= 180.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--units" => options.units = Some(Units::from_name(value).ok_or_else(invalid)?),
+            "--provider" => options.provider = Some(provider_name(value, flag)?),
+            "--seed" => options.seed = Some(value.parse().map_err(|_| invalid())?),
+            "--cache-ttl" => options.cache_ttl = Some(value.parse().map_err(|_| invalid())?),
+            "--watch" => {
+                options.watch = Some(
+                    parse_seconds(value)
+                        .filter(|s| *s > 0.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--fade" => options.fade = Some(parse_seconds(value).ok_or_else(invalid)?),
+            "--max-backoff" => {
+                options.max_backoff = Some(
+                    parse_seconds(value)
+                        .filter(|s| *s > 0.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--render" | "--output" | "-o" => output = Some(PathBuf::from(value)),
+            "--seconds" => {
+                seconds = Some(
+                    value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|seconds| *seconds > 0.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--fps" => {
+                fps = Some(
+                    value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|fps| (1..=100).contains(fps))
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "--size" => size = Some(parse_size(value).ok_or_else(invalid)?),
+            _ => return Err(format!("Unknown option '{}'.", flag)),
+        }
+    }
+
+    if options.provider.is_none() {
+        if let Some(name) = env::var("WEATHER_PROVIDER")
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+        {
+            provider_name(name.trim(), "WEATHER_PROVIDER")?;
+        }
+    }
+
+    options.location = match (city, lat, lon) {
+        (Some(city), None, None) => Some(Location::City(city)),
+        (None, Some(lat), Some(lon)) => Some(Location::Coords { lat, lon }),
+        (None, None, None) => None,
+        (Some(_), _, _) => return Err("Use either --city or --lat and --lon, not both.".into()),
+        _ => return Err("--lat and --lon must be given together.".into()),
+    };
+
+    // The older form without a subcommand renders when it has an output
+    let subcommand = match subcommand {
+        None if output.is_some() => Some("render"),
+        None if options.location.is_some() => Some("show"),
+        subcommand => subcommand,
+    };
+    if subcommand != Some("render")
+        && (output.is_some() || seconds.is_some() || fps.is_some() || size.is_some())
+    {
+        return Err("--output, --seconds, --fps and --size only work with render.".into());
+    }
+    if matches!(subcommand, Some("render" | "forecast"))
+        && (options.watch.is_some() || options.fade.is_some() || options.max_backoff.is_some())
+    {
+        return Err("--watch, --fade and --max-backoff only work with the window.".into());
+    }
+    if subcommand.is_some() && options.location.is_none() {
+        return Err("Choose a place with --city or with --lat and --lon.".into());
+    }
+
+    Ok(match subcommand {
+        Some("show") => Command::Show(options),
+        Some("forecast") => Command::Forecast(options),
+        Some("render") => Command::Render(RenderOptions {
+            options,
+            output: output.ok_or("Choose the file to render to.")?,
+            seconds,
+            fps: fps.unwrap_or(defaults.fps),
+            size: size.unwrap_or(defaults.size),
+        }),
+        _ => Command::Interactive(options),
+    })
+}
+
+/// The function that reads a number of seconds that is not negative, such as "2.5".
+fn parse_seconds(value: &str) -> Option<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+}
+
+/// The function that reads an image size such as "800x400".
+pub fn parse_size(value: &str) -> Option<[u32; 2]> {
+    let (width, height) = value.split_once('x')?;
+    let size = [width.trim().parse().ok()?, height.trim().parse().ok()?];
+    if size.contains(&0) {
+        return None;
+    }
+    Some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn london() -> Options {
+        Options {
+            location: Some(Location::City("London".to_string())),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_interactive_by_default() {
+        assert_eq!(
+            parse_args(&[]),
+            Ok(Command::Interactive(Options::default()))
+        );
+        assert_eq!(
+            parse_args(&["--units", "imperial", "--seed", "3", "--cache-ttl", "0"]),
+            Ok(Command::Interactive(Options {
+                units: Some(Units::Imperial),
+                seed: Some(3),
+                cache_ttl: Some(0),
+                ..Options::default()
+            }))
+        );
+        assert_eq!(parse_args(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse_args(&["help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_show_and_forecast() {
+        assert_eq!(
+            parse_args(&["show", "--city", "London"]),
+            Ok(Command::Show(london()))
+        );
+        assert_eq!(
+            parse_args(&["--city", "London"]),
+            Ok(Command::Show(london()))
+        );
+        assert_eq!(
+            parse_args(&[
+                "forecast",
+                "--lat",
+                "40.4",
+                "--lon",
+                "-3.7",
+                "--provider",
+                "nws"
+            ]),
+            Ok(Command::Forecast(Options {
+                location: Some(Location::Coords {
+                    lat: 40.4,
+                    lon: -3.7
+                }),
+                provider: Some("nws".to_string()),
+                ..Options::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_watch() {
+        let args = [
+            "show",
+            "--city",
+            "London",
+            "--watch",
+            "300",
+            "--fade",
+            "0",
+            "--max-backoff",
+            "900",
+        ];
+        let Ok(Command::Show(options)) = parse_args(&args) else {
+            panic!("Expected the show command");
+        };
+        assert_eq!(
+            options.watch(),
+            Some(Watch {
+                interval: 300.0,
+                fade: 0.0,
+                max_backoff: 900.0,
+            })
+        );
+        assert_eq!(london().watch, None);
+
+        assert!(parse_args(&["show", "--city", "London", "--watch", "0"]).is_err());
+        assert!(parse_args(&["show", "--city", "London", "--fade", "-1"]).is_err());
+        assert!(parse_args(&["forecast", "--city", "London", "--watch", "60"]).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            parse_args(&[
+                "render",
+                "ny.gif",
+                "--city",
+                "London",
+                "--seconds",
+                "2.5",
+                "--fps",
+                "12",
+                "--size",
+                "640x320"
+            ]),
+            Ok(Command::Render(RenderOptions {
+                options: london(),
+                output: PathBuf::from("ny.gif"),
+                seconds: Some(2.5),
+                fps: 12,
+                size: [640, 320],
+            }))
+        );
+        // The older form without a subcommand
+        assert_eq!(
+            parse_args(&["--render", "out.png", "--city", "London"]),
+            Ok(Command::Render(RenderOptions {
+                options: london(),
+                output: PathBuf::from("out.png"),
+                seconds: None,
+                fps: 20,
+                size: [1024, 512],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse_args(&["show"]).is_err());
+        assert!(parse_args(&["render", "--city", "London"]).is_err());
+        assert!(parse_args(&["show", "--city"]).is_err());
+        assert!(parse_args(&["show", "--lat", "40.4", "--city", "Madrid"]).is_err());
+        assert!(parse_args(&["show", "--lat", "40.4"]).is_err());
+        assert!(parse_args(&["show", "--lat", "140", "--lon", "0"]).is_err());
+        assert!(parse_args(&["show", "--city", "Kyoto", "--fps", "10"]).is_err());
+        assert!(parse_args(&["show", "--city", "Kyoto", "--units", "furlongs"]).is_err());
+        assert!(parse_args(&["show", "--city", "Kyoto", "--provider", "accuweather"]).is_err());
+        assert!(parse_args(&["show", "--city", "Kyoto", "--cache-ttl", "-1"]).is_err());
+        assert!(parse_args(&["render", "out.gif", "--city", "Kyoto", "--size", "10"]).is_err());
+        assert!(parse_args(&["render", "out.gif", "--city", "Kyoto", "--fps", "0"]).is_err());
+        assert!(parse_args(&["--render", "out.png", "--zoom", "2"]).is_err());
+        assert!(parse_args(&["watch", "--city", "Kyoto"]).is_err());
+    }
+
+    #[test]
+    fn test_provider_names() {
+        let provider = |name: &str| {
+            parse_args(&["show", "--city", "Kyoto", "--provider", name]).map(
+                |command| match command {
+                    Command::Show(options) => options.provider,
+                    _ => None,
+                },
+            )
+        };
+        assert_eq!(provider("OpenMeteo"), Ok(Some("open-meteo".to_string())));
+        assert_eq!(provider("weather.gov"), Ok(Some("nws".to_string())));
+        assert_eq!(provider("OWM"), Ok(Some("openweathermap".to_string())));
+        assert_eq!(
+            provider("accuweather"),
+            Err("Invalid value 'accuweather' for --provider, choose one of openweathermap|open-meteo|nws|metar.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("800x400"), Some([800, 400]));
+        assert_eq!(parse_size("800"), None);
+        assert_eq!(parse_size("0x400"), None);
+        assert_eq!(parse_size("wide x tall"), None);
+    }
+}
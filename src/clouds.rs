@@ -0,0 +1,148 @@
+//! Procedurally generated clouds.
+//!
+//! The sky band is split into a grid of cells and every cell samples a fractal Perlin noise field. The
+//! cells with the highest noise become cloud puffs, as many as the reported cloud percentage asks for,
+//! so the cloud shapes are stable from frame to frame and slowly evolve as the noise field moves with
+//! time and wind.
+
+use nannou::noise::{Fbm, NoiseFn, Seedable};
+use nannou::prelude::*;
+
+/// The size of a grid cell of the sky in pixels.
+const CELL_SIZE: f32 = 40.0;
+
+/// How far apart in the noise field neighbouring cells are. Smaller values give larger clouds.
+const NOISE_SCALE: f64 = 0.012;
+
+/// How fast the cloud shapes change, in noise units per second.
+const EVOLVE_SPEED: f64 = 0.05;
+
+/// A single round part of a cloud.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Puff {
+    pub center: Point2,
+    pub radius: f32,
+    /// The opacity from 0 to 1.
+    pub alpha: f32,
+}
+
+/// The clouds of a scene.
+pub struct CloudField {
+    noise: Fbm,
+    /// The part of the sky covered by clouds, from 0 to 1.
+    coverage: f32,
+    /// Whether the clouds are dark rain clouds.
+    dark: bool,
+}
+
+impl CloudField {
+    /// The function that creates the clouds for the cloud percentage from 0 to 100.
+    /// The same seed always gives the same cloud shapes.
+    pub fn new(percent: i64, dark: bool, seed: u32) -> CloudField {
+        CloudField {
+            noise: Fbm::new().set_seed(seed),
+            coverage: percent.clamp(0, 100) as f32 / 100.0,
+            dark,
+        }
+    }
+
+    /// The function that returns the band of the sky the clouds are in.
+    /// More clouds hang lower and make a thicker band.
+    pub fn band(&self, win: Rect) -> Rect {
+        let height = (120.0 + 180.0 * self.coverage).min(win.h());
+        Rect::from_x_y_w_h(win.x(), win.top() - height / 2.0, win.w(), height)
+    }
+
+    /// The function that returns the cloud puffs after `time` seconds with the clouds moved `offset`
+    /// pixels sideways by the wind.
+    pub fn puffs(&self, win: Rect, offset: f32, time: f32) -> Vec<Puff> {
+        let band = self.band(win);
+        let cols = (band.w() / CELL_SIZE).ceil() as usize;
+        let rows = (band.h() / CELL_SIZE).ceil() as usize;
+        let z = time as f64 * EVOLVE_SPEED;
+
+        let mut cells = Vec::with_capacity(cols * rows);
+        for row in 0..rows {
+            for col in 0..cols {
+                let x = band.left() + (col as f32 + 0.5) * CELL_SIZE;
+                let y = band.top() - (row as f32 + 0.5) * CELL_SIZE;
+                let sample = [(x - offset) as f64 * NOISE_SCALE, y as f64 * NOISE_SCALE, z];
+                cells.push((pt2(x, y), self.noise.get(sample)));
+            }
+        }
+
+        // The cells with the most noise become clouds, so exactly the reported part of the sky is covered
+        let n_cloudy = (cells.len() as f32 * self.coverage).round() as usize;
+        cells.sort_by(|a, b| b.1.total_cmp(&a.1));
+        cells.truncate(n_cloudy);
+
+        let opacity = 0.55 + 0.4 * self.coverage;
+        let lowest = cells.last().map(|cell| cell.1).unwrap_or(0.0);
+        let highest = cells.first().map(|cell| cell.1).unwrap_or(0.0);
+        let spread = (highest - lowest).max(f64::EPSILON);
+
+        cells
+            .into_iter()
+            .map(|(center, value)| {
+                let strength = ((value - lowest) / spread) as f32;
+                Puff {
+                    center,
+                    radius: CELL_SIZE * (0.8 + 0.6 * strength),
+                    alpha: opacity * (0.6 + 0.4 * strength),
+                }
+            })
+            .collect()
+    }
+
+    /// The function that draws the clouds.
+    pub fn draw(&self, draw: &Draw, win: Rect, offset: f32, time: f32) {
+        let shade = if self.dark { 0.41 } else { 0.83 };
+        for puff in self.puffs(win, offset, time) {
+            draw.ellipse()
+                .xy(puff.center)
+                .w_h(puff.radius * 2.0, puff.radius * 1.4)
+                .color(rgba(shade, shade, shade, puff.alpha));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win() -> Rect {
+        Rect::from_w_h(1024.0, 512.0)
+    }
+
+    #[test]
+    fn test_coverage_sets_puff_count() {
+        let clear = CloudField::new(0, false, 1).puffs(win(), 0.0, 0.0);
+        let some = CloudField::new(37, false, 1).puffs(win(), 0.0, 0.0);
+        let more = CloudField::new(41, false, 1).puffs(win(), 0.0, 0.0);
+        let overcast = CloudField::new(100, false, 1).puffs(win(), 0.0, 0.0);
+
+        assert!(clear.is_empty());
+        assert!(some.len() < more.len());
+        assert!(more.len() < overcast.len());
+    }
+
+    #[test]
+    fn test_clouds_are_stable() {
+        let field = CloudField::new(60, true, 7);
+
+        assert_eq!(field.puffs(win(), 10.0, 3.0), field.puffs(win(), 10.0, 3.0));
+        assert_ne!(
+            field.puffs(win(), 10.0, 3.0),
+            field.puffs(win(), 10.0, 30.0)
+        );
+    }
+
+    #[test]
+    fn test_more_clouds_hang_lower() {
+        let few = CloudField::new(10, false, 1).band(win());
+        let many = CloudField::new(90, false, 1).band(win());
+
+        assert_eq!(few.top(), win().top());
+        assert!(many.bottom() < few.bottom());
+    }
+}
@@ -0,0 +1,194 @@
+//! Classification of weather condition codes into the scenes the app knows how to draw.
+
+/// How strong a condition is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+}
+
+/// How much of the sky is covered by clouds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudCover {
+    /// 11-25% of the sky.
+    Few,
+    /// 25-50% of the sky.
+    Scattered,
+    /// 51-84% of the sky.
+    Broken,
+    /// 85-100% of the sky.
+    Overcast,
+}
+
+/// A weather condition the app has a visualization for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Thunderstorm(Intensity),
+    Drizzle(Intensity),
+    Rain(Intensity),
+    FreezingRain,
+    Snow(Intensity),
+    /// Sleet and mixed rain and snow.
+    Sleet(Intensity),
+    Mist,
+    Smoke,
+    Haze,
+    Dust,
+    Fog,
+    Sand,
+    Ash,
+    Squall,
+    Tornado,
+    Clear,
+    Clouds(CloudCover),
+    /// A code without a visualization, kept so it can be shown to the user.
+    Unknown(i64),
+}
+
+impl Intensity {
+    /// The function that picks the value matching the intensity.
+    pub fn pick<T>(self, light: T, moderate: T, heavy: T) -> T {
+        match self {
+            Intensity::Light => light,
+            Intensity::Moderate => moderate,
+            Intensity::Heavy => heavy,
+        }
+    }
+}
+
+impl Condition {
+    /// The function that classifies an OpenWeatherMap condition id.
+    /// Every documented code from https://openweathermap.org/weather-conditions has a deliberate mapping,
+    /// anything else becomes `Condition::Unknown`.
+    pub fn from_owm_id(id: i64) -> Condition {
+        use Condition::*;
+        use Intensity::*;
+
+        match id {
+            // thunderstorms
+            200 | 210 | 230 => Thunderstorm(Light),
+            201 | 211 | 231 => Thunderstorm(Moderate),
+            202 | 212 | 221 | 232 => Thunderstorm(Heavy),
+            // drizzle
+            300 | 310 => Drizzle(Light),
+            301 | 311 | 313 | 321 => Drizzle(Moderate),
+            302 | 312 | 314 => Drizzle(Heavy),
+            // rain
+            500 | 520 => Rain(Light),
+            501 | 521 | 531 => Rain(Moderate),
+            502 | 503 | 504 | 522 => Rain(Heavy),
+            511 => FreezingRain,
+            // snow
+            600 | 620 => Snow(Light),
+            601 | 621 => Snow(Moderate),
+            602 | 622 => Snow(Heavy),
+            612 | 615 => Sleet(Light),
+            611 | 613 | 616 => Sleet(Moderate),
+            // atmosphere
+            701 => Mist,
+            711 => Smoke,
+            721 => Haze,
+            731 | 761 => Dust,
+            741 => Fog,
+            751 => Sand,
+            762 => Ash,
+            771 => Squall,
+            781 => Tornado,
+            // clear and clouds
+            800 => Clear,
+            801 => Clouds(CloudCover::Few),
+            802 => Clouds(CloudCover::Scattered),
+            803 => Clouds(CloudCover::Broken),
+            804 => Clouds(CloudCover::Overcast),
+            _ => Unknown(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CloudCover::*;
+    use Condition::*;
+    use Intensity::*;
+
+    #[test]
+    fn test_every_documented_code() {
+        let table = [
+            (200, Thunderstorm(Light)),
+            (201, Thunderstorm(Moderate)),
+            (202, Thunderstorm(Heavy)),
+            (210, Thunderstorm(Light)),
+            (211, Thunderstorm(Moderate)),
+            (212, Thunderstorm(Heavy)),
+            (221, Thunderstorm(Heavy)),
+            (230, Thunderstorm(Light)),
+            (231, Thunderstorm(Moderate)),
+            (232, Thunderstorm(Heavy)),
+            (300, Drizzle(Light)),
+            (301, Drizzle(Moderate)),
+            (302, Drizzle(Heavy)),
+            (310, Drizzle(Light)),
+            (311, Drizzle(Moderate)),
+            (312, Drizzle(Heavy)),
+            (313, Drizzle(Moderate)),
+            (314, Drizzle(Heavy)),
+            (321, Drizzle(Moderate)),
+            (500, Rain(Light)),
+            (501, Rain(Moderate)),
+            (502, Rain(Heavy)),
+            (503, Rain(Heavy)),
+            (504, Rain(Heavy)),
+            (511, FreezingRain),
+            (520, Rain(Light)),
+            (521, Rain(Moderate)),
+            (522, Rain(Heavy)),
+            (531, Rain(Moderate)),
+            (600, Snow(Light)),
+            (601, Snow(Moderate)),
+            (602, Snow(Heavy)),
+            (611, Sleet(Moderate)),
+            (612, Sleet(Light)),
+            (613, Sleet(Moderate)),
+            (615, Sleet(Light)),
+            (616, Sleet(Moderate)),
+            (620, Snow(Light)),
+            (621, Snow(Moderate)),
+            (622, Snow(Heavy)),
+            (701, Mist),
+            (711, Smoke),
+            (721, Haze),
+            (731, Dust),
+            (741, Fog),
+            (751, Sand),
+            (761, Dust),
+            (762, Ash),
+            (771, Squall),
+            (781, Tornado),
+            (800, Clear),
+            (801, Clouds(Few)),
+            (802, Clouds(Scattered)),
+            (803, Clouds(Broken)),
+            (804, Clouds(Overcast)),
+        ];
+
+        for (id, condition) in table {
+            assert_eq!(Condition::from_owm_id(id), condition, "code {}", id);
+        }
+    }
+
+    #[test]
+    fn test_unknown_codes() {
+        assert_eq!(Condition::from_owm_id(0), Unknown(0));
+        assert_eq!(Condition::from_owm_id(299), Unknown(299));
+        assert_eq!(Condition::from_owm_id(805), Unknown(805));
+    }
+
+    #[test]
+    fn test_pick() {
+        assert_eq!(Light.pick(1, 2, 3), 1);
+        assert_eq!(Moderate.pick(1, 2, 3), 2);
+        assert_eq!(Heavy.pick(1, 2, 3), 3);
+    }
+}
@@ -0,0 +1,240 @@
+//! The time of day at the city of a weather report.
+//!
+//! The report's sunrise and sunset are used to place the sun on an arc over the day and the moon on an
+//! arc over the night. Reports without sunrise and sunset, such as some forecasts, assume the sun rises
+//! at 6:00 and sets at 18:00 local time. Reports with coordinates take the height and direction of the sun
+//! from `astronomy`, so a winter sun stays low and the sun is in the right part of the sky even without
+//! sunrise and sunset.
+
+use crate::astronomy::{self, MoonPhase};
+use crate::report::{Coord, WeatherReport};
+use std::f32::consts::PI;
+
+/// The seconds in a day.
+const DAY: i64 = 86_400;
+
+/// The elevation in degrees the sun climbs to at noon when the report has no coordinates, and the elevation
+/// drawn at the top of the sky.
+pub const MAX_ELEVATION: f32 = 60.0;
+
+/// The elevation in degrees below which civil twilight ends and it is night.
+pub const TWILIGHT_ELEVATION: f32 = 6.0;
+
+/// The part of the day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Night,
+    /// The twilight around sunrise.
+    Dawn,
+    Day,
+    /// The twilight around sunset.
+    Dusk,
+}
+
+/// Where the sun is at the time of a weather report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Daylight {
+    /// The elevation of the sun in degrees, negative below the horizon.
+    pub elevation: f32,
+    /// How far through the day, or through the night once the sun has set, from 0 to 1.
+    pub progress: f32,
+    /// How far across the sky from the left the sun is by day, or the moon by night, from 0 to 1.
+    /// Both rise on the left and set on the right.
+    pub across: f32,
+    pub phase: Phase,
+    pub moon: MoonPhase,
+}
+
+impl Daylight {
+    /// The function that works out where the sun is at the time of the report.
+    /// Forecast slots on later days use the sunrise and sunset of the report's day, moved to the slot's day.
+    /// The elevation of the sun is calculated from the coordinates when the report has them.
+    pub fn from_report(report: &WeatherReport) -> Daylight {
+        let (sunrise, sunset) = sun_times(report);
+        let day_length = (sunset - sunrise).clamp(1, DAY - 1);
+        let since_sunrise = (report.dt - sunrise).rem_euclid(DAY);
+
+        let (arc_elevation, progress) = if since_sunrise < day_length {
+            let progress = since_sunrise as f32 / day_length as f32;
+            ((PI * progress).sin() * MAX_ELEVATION, progress)
+        } else {
+            let progress = (since_sunrise - day_length) as f32 / (DAY - day_length) as f32;
+            (-(PI * progress).sin() * MAX_ELEVATION, progress)
+        };
+
+        let (elevation, across) = if report.coord == Coord::default() {
+            (arc_elevation, progress)
+        } else {
+            let sun = astronomy::sun_position(report.coord.lat, report.coord.lon, report.dt);
+            (
+                sun.elevation as f32,
+                across_sky(sun.azimuth, sun.elevation > 0.0),
+            )
+        };
+
+        let phase = if elevation > TWILIGHT_ELEVATION {
+            Phase::Day
+        } else if elevation < -TWILIGHT_ELEVATION {
+            Phase::Night
+        } else if (elevation >= 0.0) == (progress < 0.5) {
+            Phase::Dawn
+        } else {
+            Phase::Dusk
+        };
+
+        Daylight {
+            elevation,
+            progress,
+            across,
+            phase,
+            moon: astronomy::moon_phase(report.dt),
+        }
+    }
+
+    /// The function that returns whether the sun is above the horizon.
+    pub fn is_day(&self) -> bool {
+        self.elevation > 0.0
+    }
+
+    /// The function that returns how dark the scene is, from 0 at daytime to 1 at night.
+    /// The darkness changes smoothly through twilight.
+    pub fn darkness(&self) -> f32 {
+        ((TWILIGHT_ELEVATION - self.elevation) / (2.0 * TWILIGHT_ELEVATION)).clamp(0.0, 1.0)
+    }
+
+    /// The function that returns how strong the twilight glow is, from 0 to 1, highest at sunrise and sunset.
+    pub fn twilight(&self) -> f32 {
+        (1.0 - self.elevation.abs() / TWILIGHT_ELEVATION).max(0.0)
+    }
+}
+
+/// The function that returns how far across the sky from the left the sun is for its azimuth in degrees,
+/// or the moon at night, which is drawn opposite the sun.
+/// Only the east to west part of the direction counts, so the sun rises on the left and sets on the right
+/// whether it passes through the south or, south of the tropics, through the north.
+fn across_sky(azimuth: f64, is_day: bool) -> f32 {
+    let east = azimuth.to_radians().sin() as f32;
+    if is_day {
+        (1.0 - east) / 2.0
+    } else {
+        (1.0 + east) / 2.0
+    }
+}
+
+/// The function that returns the sunrise and sunset of the report as unix timestamps.
+fn sun_times(report: &WeatherReport) -> (i64, i64) {
+    if report.sys.sunrise != 0 && report.sys.sunset > report.sys.sunrise {
+        return (report.sys.sunrise, report.sys.sunset);
+    }
+
+    let midnight = (report.dt + report.timezone).div_euclid(DAY) * DAY - report.timezone;
+    (midnight + 6 * 3600, midnight + 18 * 3600)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(dt: i64, sunrise: i64, sunset: i64, timezone: i64) -> WeatherReport {
+        let mut report: WeatherReport =
+            serde_json::from_str(r#"{"main": {"temp": 10.0}}"#).unwrap();
+        report.dt = dt;
+        report.sys.sunrise = sunrise;
+        report.sys.sunset = sunset;
+        report.timezone = timezone;
+        report
+    }
+
+    // London on 2023-11-14: sunrise 07:16 and sunset 16:13 UTC
+    const SUNRISE: i64 = 1699946160;
+    const SUNSET: i64 = 1699978380;
+
+    #[test]
+    fn test_midday_and_night() {
+        let noon = Daylight::from_report(&report((SUNRISE + SUNSET) / 2, SUNRISE, SUNSET, 0));
+        assert_eq!(noon.phase, Phase::Day);
+        assert!((noon.elevation - MAX_ELEVATION).abs() < 0.1);
+        assert_eq!(noon.darkness(), 0.0);
+
+        // 2 a.m. the next night
+        let night = Daylight::from_report(&report(1700013600, SUNRISE, SUNSET, 0));
+        assert_eq!(night.phase, Phase::Night);
+        assert!(!night.is_day());
+        assert_eq!(night.darkness(), 1.0);
+    }
+
+    #[test]
+    fn test_twilight() {
+        let dawn = Daylight::from_report(&report(SUNRISE + 300, SUNRISE, SUNSET, 0));
+        assert_eq!(dawn.phase, Phase::Dawn);
+        assert!(dawn.twilight() > 0.5);
+
+        let dusk = Daylight::from_report(&report(SUNSET - 300, SUNRISE, SUNSET, 0));
+        assert_eq!(dusk.phase, Phase::Dusk);
+
+        let after_sunset = Daylight::from_report(&report(SUNSET + 300, SUNRISE, SUNSET, 0));
+        assert_eq!(after_sunset.phase, Phase::Dusk);
+        assert!(after_sunset.darkness() > 0.5);
+    }
+
+    #[test]
+    fn test_elevation_from_coordinates() {
+        let mut london = report((SUNRISE + SUNSET) / 2, SUNRISE, SUNSET, 0);
+        london.coord = Coord {
+            lat: 51.5085,
+            lon: -0.1257,
+        };
+
+        // The November sun stays about 20 degrees high at noon
+        let noon = Daylight::from_report(&london);
+        assert_eq!(noon.phase, Phase::Day);
+        assert!(noon.elevation > 18.0 && noon.elevation < 23.0);
+
+        london.dt = 1700013600;
+        assert_eq!(Daylight::from_report(&london).phase, Phase::Night);
+    }
+
+    #[test]
+    fn test_forecast_days_and_missing_times() {
+        // Noon two days after the report's sunrise and sunset
+        let later = Daylight::from_report(&report(
+            (SUNRISE + SUNSET) / 2 + 2 * DAY,
+            SUNRISE,
+            SUNSET,
+            0,
+        ));
+        assert_eq!(later.phase, Phase::Day);
+
+        // 13:00 local time in a city nine hours ahead of UTC, without sunrise and sunset
+        let kyoto = Daylight::from_report(&report(1700020800, 0, 0, 9 * 3600));
+        assert_eq!(kyoto.phase, Phase::Day);
+        assert!(kyoto.progress > 0.5);
+    }
+
+    #[test]
+    fn test_across_sky_from_coordinates() {
+        // Nashville on 2023-11-14 without sunrise and sunset, as the NWS reports it
+        let mut nashville = report(1699974000, 0, 0, -6 * 3600);
+        nashville.coord = Coord {
+            lat: 36.1622,
+            lon: -86.7744,
+        };
+
+        // At 9:00 the sun is in the south east, at 15:00 in the south west
+        let morning = Daylight::from_report(&nashville);
+        assert!(morning.is_day());
+        assert!(morning.across > 0.1 && morning.across < 0.4);
+        nashville.dt += 6 * 3600;
+        let afternoon = Daylight::from_report(&nashville);
+        assert!(afternoon.across > 0.6 && afternoon.across < 0.9);
+
+        // In Sydney the sun at solar noon, 12:40, is in the north, still half way across
+        let mut sydney = report(1699926000, 0, 0, 11 * 3600);
+        sydney.coord = Coord {
+            lat: -33.8688,
+            lon: 151.2093,
+        };
+        let noon = Daylight::from_report(&sydney);
+        assert!((noon.across - 0.5).abs() < 0.1);
+    }
+}
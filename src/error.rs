@@ -0,0 +1,123 @@
+use reqwest::StatusCode;
+use std::fmt;
+
+/// The errors that can happen while fetching the weather for a city.
+/// These are shown on screen instead of crashing the visualization.
+#[derive(Debug)]
+pub enum WeatherError {
+    /// The `API_KEY` environment variable is not set.
+    MissingApiKey,
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+    /// The API rejected the key (HTTP 401).
+    Unauthorized,
+    /// The requested city does not exist (HTTP 404).
+    NotFound,
+    /// Too many requests were made with the key (HTTP 429).
+    RateLimited,
+    /// Any other unsuccessful HTTP status.
+    Status(u16),
+    /// The response body did not have the expected shape.
+    Decode(serde_json::Error),
+    /// The response was valid JSON but a required field was missing.
+    MissingField(&'static str),
+    /// A METAR report could not be decoded.
+    InvalidMetar(String),
+    /// The provider does not offer the requested data, such as a forecast from a METAR.
+    Unsupported(&'static str),
+    /// A local file with weather data could not be read.
+    Io(std::io::Error),
+    /// An image of the scene could not be rendered or saved.
+    Render(String),
+}
+
+impl WeatherError {
+    /// The function that turns an unsuccessful HTTP status into the matching error.
+    pub fn from_status(status: StatusCode) -> WeatherError {
+        match status {
+            StatusCode::UNAUTHORIZED => WeatherError::Unauthorized,
+            StatusCode::NOT_FOUND => WeatherError::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => WeatherError::RateLimited,
+            _ => WeatherError::Status(status.as_u16()),
+        }
+    }
+}
+
+impl fmt::Display for WeatherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeatherError::MissingApiKey => {
+                write!(f, "API_KEY is not set, add it to your .env file")
+            }
+            WeatherError::Transport(e) => write!(f, "Could not reach the weather service: {}", e),
+            WeatherError::Unauthorized => write!(f, "The API key was rejected (401)"),
+            WeatherError::NotFound => write!(f, "City not found (404)"),
+            WeatherError::RateLimited => write!(f, "Too many requests, try again later (429)"),
+            WeatherError::Status(code) => write!(f, "The weather service returned HTTP {}", code),
+            WeatherError::Decode(e) => write!(f, "Could not read the weather data: {}", e),
+            WeatherError::MissingField(field) => {
+                write!(f, "The weather data is missing the '{}' field", field)
+            }
+            WeatherError::InvalidMetar(reason) => {
+                write!(f, "Could not decode the METAR, {}", reason)
+            }
+            WeatherError::Unsupported(what) => write!(f, "This provider does not support {}", what),
+            WeatherError::Io(e) => write!(f, "Could not read the weather data: {}", e),
+            WeatherError::Render(reason) => write!(f, "Could not render the image, {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for WeatherError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WeatherError::Transport(e) => Some(e),
+            WeatherError::Decode(e) => Some(e),
+            WeatherError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for WeatherError {
+    fn from(e: reqwest::Error) -> WeatherError {
+        WeatherError::Transport(e)
+    }
+}
+
+impl From<std::io::Error> for WeatherError {
+    fn from(e: std::io::Error) -> WeatherError {
+        WeatherError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for WeatherError {
+    fn from(e: serde_json::Error) -> WeatherError {
+        WeatherError::Decode(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert!(matches!(
+            WeatherError::from_status(StatusCode::UNAUTHORIZED),
+            WeatherError::Unauthorized
+        ));
+        assert!(matches!(
+            WeatherError::from_status(StatusCode::NOT_FOUND),
+            WeatherError::NotFound
+        ));
+        assert!(matches!(
+            WeatherError::from_status(StatusCode::TOO_MANY_REQUESTS),
+            WeatherError::RateLimited
+        ));
+        assert!(matches!(
+            WeatherError::from_status(StatusCode::BAD_GATEWAY),
+            WeatherError::Status(502)
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! Writing rendered frames as animations.
+//!
+//! Animations can be saved as a looping GIF, a looping APNG or a numbered sequence of PNG files, such as
+//! `loop_0000.png`, `loop_0001.png` and so on. The image crate can not write APNG, so every frame is
+//! encoded as a PNG and its image data is copied into the animation chunks of the APNG format.
+
+use crate::error::WeatherError;
+use nannou::image::codecs::gif::{GifEncoder, Repeat};
+use nannou::image::codecs::png::PngEncoder;
+use nannou::image::{ColorType, Delay, Frame, RgbaImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The bytes every PNG file starts with.
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The file format of an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Gif,
+    Apng,
+    /// One PNG file per frame, numbered from 0.
+    PngSequence,
+}
+
+impl Format {
+    /// The function that picks the format from the extension of the output path, `.gif`, `.apng` or `.png`.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Format::Gif),
+            "apng" => Some(Format::Apng),
+            "png" => Some(Format::PngSequence),
+            _ => None,
+        }
+    }
+}
+
+/// A writer that adds rendered frames to an animation file one at a time, so the frames do not all have
+/// to be kept in memory.
+pub enum FrameWriter {
+    Gif(GifEncoder<BufWriter<File>>, Delay),
+    Apng {
+        file: BufWriter<File>,
+        frames: u32,
+        fps: u32,
+        /// The sequence number of the next fcTL or fdAT chunk.
+        sequence: u32,
+    },
+    PngSequence {
+        path: PathBuf,
+        index: u32,
+    },
+}
+
+impl FrameWriter {
+    /// The function that creates the output for an animation of `frames` frames at `fps` frames per second.
+    pub fn create(
+        format: Format,
+        path: &Path,
+        frames: u32,
+        fps: u32,
+    ) -> Result<FrameWriter, WeatherError> {
+        let fps = fps.max(1);
+        let writer = match format {
+            Format::Gif => {
+                let mut encoder =
+                    GifEncoder::new_with_speed(BufWriter::new(create_file(path)?), 10);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(|e| export_error(path, e))?;
+                FrameWriter::Gif(encoder, Delay::from_numer_denom_ms(1000, fps))
+            }
+            Format::Apng => FrameWriter::Apng {
+                file: BufWriter::new(create_file(path)?),
+                frames,
+                fps,
+                sequence: 0,
+            },
+            Format::PngSequence => FrameWriter::PngSequence {
+                path: path.to_path_buf(),
+                index: 0,
+            },
+        };
+        Ok(writer)
+    }
+
+    /// The function that adds the next frame to the animation.
+    pub fn write_frame(&mut self, image: &RgbaImage) -> Result<(), WeatherError> {
+        match self {
+            FrameWriter::Gif(encoder, delay) => encoder
+                .encode_frame(Frame::from_parts(image.clone(), 0, 0, *delay))
+                .map_err(|e| WeatherError::Render(e.to_string())),
+            FrameWriter::Apng {
+                file,
+                frames,
+                fps,
+                sequence,
+            } => write_apng_frame(file, image, *frames, *fps, sequence)
+                .map_err(|e| WeatherError::Render(e.to_string())),
+            FrameWriter::PngSequence { path, index } => {
+                let frame_path = sequence_path(path, *index);
+                *index += 1;
+                image
+                    .save(&frame_path)
+                    .map_err(|e| export_error(&frame_path, e))
+            }
+        }
+    }
+
+    /// The function that completes the animation file.
+    pub fn finish(self) -> Result<(), WeatherError> {
+        let result = match self {
+            FrameWriter::Gif(encoder, _) => {
+                drop(encoder);
+                Ok(())
+            }
+            FrameWriter::Apng { mut file, .. } => {
+                write_chunk(&mut file, b"IEND", &[]).and_then(|_| file.flush())
+            }
+            FrameWriter::PngSequence { .. } => Ok(()),
+        };
+        result.map_err(|e| WeatherError::Render(e.to_string()))
+    }
+}
+
+/// The function that returns the path of a frame in a PNG sequence, such as `loop_0007.png` for `loop.png`.
+pub fn sequence_path(path: &Path, index: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_{:04}.png", stem, index))
+}
+
+/// The function that creates the output file.
+fn create_file(path: &Path) -> Result<File, WeatherError> {
+    File::create(path).map_err(|e| export_error(path, e))
+}
+
+/// The function that describes an error writing the file at the path.
+fn export_error(path: &Path, e: impl std::fmt::Display) -> WeatherError {
+    WeatherError::Render(format!("{}: {}", path.display(), e))
+}
+
+/// The function that writes a frame of an APNG. The first frame also writes the header of the file and is
+/// the image shown by viewers without APNG support.
+fn write_apng_frame(
+    file: &mut impl Write,
+    image: &RgbaImage,
+    frames: u32,
+    fps: u32,
+    sequence: &mut u32,
+) -> std::io::Result<()> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .encode(image, image.width(), image.height(), ColorType::Rgba8)
+        .map_err(std::io::Error::other)?;
+    let chunks = png_chunks(&png);
+
+    let first = *sequence == 0;
+    if first {
+        file.write_all(&PNG_SIGNATURE)?;
+        for (kind, data) in chunks.iter().filter(|(kind, _)| kind == b"IHDR") {
+            write_chunk(file, kind, data)?;
+        }
+        // The number of frames and a play count of 0, which loops forever
+        let mut actl = frames.max(1).to_be_bytes().to_vec();
+        actl.extend_from_slice(&0u32.to_be_bytes());
+        write_chunk(file, b"acTL", &actl)?;
+    }
+
+    // The frame control: size, offset, a delay of 1/fps seconds, no disposal and no blending
+    let mut fctl = Vec::with_capacity(26);
+    for value in [*sequence, image.width(), image.height(), 0, 0] {
+        fctl.extend_from_slice(&value.to_be_bytes());
+    }
+    fctl.extend_from_slice(&1u16.to_be_bytes());
+    fctl.extend_from_slice(&(fps as u16).to_be_bytes());
+    fctl.extend_from_slice(&[0, 0]);
+    write_chunk(file, b"fcTL", &fctl)?;
+    *sequence += 1;
+
+    for (_, data) in chunks.iter().filter(|(kind, _)| kind == b"IDAT") {
+        if first {
+            write_chunk(file, b"IDAT", data)?;
+        } else {
+            let mut fdat = sequence.to_be_bytes().to_vec();
+            fdat.extend_from_slice(data);
+            write_chunk(file, b"fdAT", &fdat)?;
+            *sequence += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// The function that splits an encoded PNG into the type and data of its chunks.
+fn png_chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut rest = png.get(PNG_SIGNATURE.len()..).unwrap_or_default();
+
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = [rest[4], rest[5], rest[6], rest[7]];
+        let data = match rest.get(8..8 + length) {
+            Some(data) => data,
+            None => break,
+        };
+        chunks.push((kind, data));
+        rest = rest.get(12 + length..).unwrap_or_default();
+    }
+
+    chunks
+}
+
+/// The function that writes a PNG chunk with its length and checksum.
+fn write_chunk(file: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+
+    file.write_all(&(data.len() as u32).to_be_bytes())?;
+    file.write_all(kind)?;
+    file.write_all(data)?;
+    file.write_all(&hasher.finalize().to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::image::codecs::png::PngDecoder;
+    use nannou::image::{AnimationDecoder, Rgba};
+
+    fn frame(shade: u8) -> RgbaImage {
+        RgbaImage::from_pixel(8, 4, Rgba([shade, shade, shade, 255]))
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("loop.gif")), Some(Format::Gif));
+        assert_eq!(
+            Format::from_path(Path::new("loop.APNG")),
+            Some(Format::Apng)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("frames/loop.png")),
+            Some(Format::PngSequence)
+        );
+        assert_eq!(Format::from_path(Path::new("loop.mp4")), None);
+        assert_eq!(
+            sequence_path(Path::new("frames/loop.png"), 7),
+            PathBuf::from("frames/loop_0007.png")
+        );
+    }
+
+    #[test]
+    fn test_apng_round_trip() {
+        let path = std::env::temp_dir().join("weather_visualizer_test_export.apng");
+        let mut writer = FrameWriter::create(Format::Apng, &path, 3, 10).unwrap();
+        for shade in [0, 100, 200] {
+            writer.write_frame(&frame(shade)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let decoder = PngDecoder::new(File::open(&path).unwrap()).unwrap();
+        assert!(decoder.is_apng());
+        let frames = decoder.apng().into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[1].buffer().get_pixel(0, 0),
+            &Rgba([100, 100, 100, 255])
+        );
+        assert_eq!(frames[2].delay().numer_denom_ms(), (100, 1));
+    }
+
+    #[test]
+    fn test_gif_frames() {
+        let path = std::env::temp_dir().join("weather_visualizer_test_export.gif");
+        let mut writer = FrameWriter::create(Format::Gif, &path, 2, 20).unwrap();
+        writer.write_frame(&frame(0)).unwrap();
+        writer.write_frame(&frame(255)).unwrap();
+        writer.finish().unwrap();
+
+        let decoder =
+            nannou::image::codecs::gif::GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].buffer().dimensions(), (8, 4));
+    }
+}
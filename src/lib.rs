@@ -0,0 +1,21 @@
+//! The weather fetching and data model used by the weather visualizer.
+
+pub mod astronomy;
+pub mod cache;
+pub mod cli;
+pub mod clouds;
+pub mod condition;
+pub mod daylight;
+pub mod error;
+pub mod export;
+pub mod metar;
+pub mod particles;
+pub mod provider;
+pub mod render;
+pub mod report;
+pub mod scene;
+pub mod search;
+pub mod units;
+pub mod watch;
+pub mod wind;
+pub mod worker;
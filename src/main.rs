@@ -1,11 +1,27 @@
 use dotenvy::dotenv;
+use nannou::draw;
 use nannou::prelude::*;
-use reqwest::blocking::Client;
-use serde_json::Value;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::SeedableRng;
+use std::cell::RefCell;
 use std::env;
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use std::{io, thread};
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use weather_visualizer::cache::{Cache, CachedProvider};
+use weather_visualizer::cli::{self, Command, Options, RenderOptions};
+use weather_visualizer::error::WeatherError;
+use weather_visualizer::export::Format;
+use weather_visualizer::provider::open_meteo::OpenMeteo;
+use weather_visualizer::provider::openweathermap::OpenWeatherMap;
+use weather_visualizer::provider::{self, Location, Place, WeatherProvider};
+use weather_visualizer::render::{self, Animation};
+use weather_visualizer::report::{Coord, WeatherReport};
+use weather_visualizer::scene::{self, Layers, SceneState};
+use weather_visualizer::search::{SearchBox, Suggestion};
+use weather_visualizer::units::Units;
+use weather_visualizer::watch::Refresher;
+use weather_visualizer::worker::Worker;
 
 /// The main model of the application.
 /// This is where you would define fields that describe the state of your application.
@@ -13,719 +29,936 @@ use std::{io, thread};
 /// application to the screen.
 struct Model {
     texture: wgpu::Texture,
-    city: ((f64, i64), String),
-    receiver: mpsc::Receiver<String>,
-    read_flag: Arc<Mutex<bool>>,
+    report: Result<WeatherReport, WeatherError>,
+    /// The thread that fetches the weather, so the window keeps drawing while it waits for the network.
+    worker: Worker<Message>,
+    /// The place whose weather is being fetched, with the time the fetch started. The previous scene is
+    /// shown until the weather arrives.
+    loading: Option<(String, f32)>,
+    /// The city search box drawn over the scene.
+    search: SearchBox,
+    /// Whether the 3-hourly forecast is shown instead of the current weather.
+    forecast_mode: bool,
+    /// The forecast slots for the current city, fetched the first time forecast mode is turned on.
+    forecast: Option<Result<Vec<WeatherReport>, WeatherError>>,
+    /// Whether the forecast for the current city is being fetched.
+    forecast_loading: bool,
+    /// The index of the forecast slot shown in the main scene.
+    selected: usize,
+    /// The particles and clouds of the shown scene.
+    scene: SceneState,
+    /// The seed from the command line or the environment, used instead of the seed of the shown report.
+    seed: Option<u64>,
+    /// The random numbers of the shown scene, seeded again whenever the shown report changes.
+    rng: StdRng,
+    /// The seed of the random numbers for drawing the current frame, drawn from `rng` on every update.
+    frame_seed: u64,
+    /// The units temperatures and wind speeds are shown in.
+    units: Units,
+    /// The place of the shown weather, fetched again in watch mode.
+    place: Option<Location>,
+    /// The schedule of the refreshes in watch mode, or `None` when the weather is only fetched once.
+    refresher: Option<Refresher>,
+    /// Whether the weather of the shown place is being fetched again.
+    refreshing: bool,
+    /// The scene before a refresh changed the weather, while it fades into the new scene.
+    fade: Option<Fade>,
 }
 
-fn main() {
-    dotenv().ok();
-
-    println!("");
-    println!("****** Welcome to Haley's Weather Visualization App! ******");
-    println!("This application provides real time visualization of the weather in a city of your choice.");
-    println!("The visualization will be displayed in a window and will include a representation of the weather conditions in the city and the temperature.");
-    println!("For a special visualization effect, choose a city from the following list: Kyoto, London, Madrid, Nashville, New York.");
-    println!("");
-    println!("Would you like to begin? (y/n):");
-
-    let mut start = String::new();
-    io::stdin()
-        .read_line(&mut start)
-        .expect("Failed to read line");
-    start = start.trim().to_string();
-
-    while start.to_lowercase() != "y" && start.to_lowercase() != "n" {
-        println!("Invalid input. Please enter 'y' to start or 'n' to exit: ");
-        start = String::new();
-        io::stdin()
-            .read_line(&mut start)
-            .expect("Failed to read line");
-        start = start.trim().to_string();
-    }
+/// The old scene of a fade between two scenes.
+struct Fade {
+    report: WeatherReport,
+    scene: SceneState,
+    started: f32,
+    seconds: f32,
+    /// The offscreen texture the old scene is drawn into every frame, laid over the new scene as it fades.
+    layer: wgpu::Texture,
+    renderer: RefCell<draw::Renderer>,
+}
 
-    if start.to_lowercase() == "y" {
-        nannou::app(model).update(update).run();
-    } else {
-        println!("Goodbye!");
+impl Fade {
+    /// The function that returns how far the fade is at `time`, from 0 to 1.
+    fn progress(&self, time: f32) -> f32 {
+        ((time - self.started) / self.seconds).clamp(0.0, 1.0)
     }
-
-    return;
 }
 
-/// The function that asks the user to the name of the city they would like the weather for.
-/// The function returns the name of the city as a String.
-fn get_city() -> String {
-    dotenv().ok();
-    let api_key = env::var("API_KEY").expect("API_KEY must be set");
+/// The results the worker thread sends back to the window.
+enum Message {
+    /// The weather for a place chosen in the search box, with the filepath of the image of the city.
+    Weather {
+        suggestion: Suggestion,
+        filepath: String,
+        report: Box<Result<WeatherReport, WeatherError>>,
+    },
+    /// The forecast for the current city.
+    Forecast(Result<Vec<WeatherReport>, WeatherError>),
+    /// The places matching a query typed in the search box.
+    Places { query: String, places: Vec<Place> },
+    /// The weather of the place fetched again in watch mode.
+    Refresh {
+        location: Location,
+        report: Box<Result<WeatherReport, WeatherError>>,
+    },
+}
 
-    println!("Enter the name of a city you would like the weather for:");
-    let mut city = String::new();
+/// The options from the command line, set before the window opens so `model` can read them.
+static OPTIONS: OnceLock<Options> = OnceLock::new();
 
-    io::stdin()
-        .read_line(&mut city)
-        .expect("Failed to read line");
-    city = city.trim().to_string();
-
-    let url = format!(
-        "https://api.openweathermap.org/data/2.5/weather?q={}&appid={}&units=metric",
-        city, api_key
-    );
+fn main() {
+    dotenv().ok();
 
-    let client = Client::new();
-    let response = client.get(&url).send().unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Render(render_options) => exit_on_error(render_image(&render_options)),
+        Command::Forecast(options) => exit_on_error(print_forecast(&options)),
+        Command::Show(options) => {
+            OPTIONS.set(options).ok();
+            nannou::app(model).update(update).run();
+        }
+        Command::Interactive(options) => {
+            OPTIONS.set(options).ok();
+            println!();
+            println!("****** Welcome to Haley's Weather Visualization App! ******");
+            println!("This application provides real time visualization of the weather in a city of your choice.");
+            println!("The visualization will be displayed in a window and will include a representation of the weather conditions in the city and the temperature.");
+            println!("For a special visualization effect, choose a city from the following list: Kyoto, London, Madrid, Nashville, New York.");
+            println!();
+            println!("Would you like to begin? (y/n):");
+
+            let mut start = String::new();
+            io::stdin()
+                .read_line(&mut start)
+                .expect("Failed to read line");
+            start = start.trim().to_string();
+
+            while start.to_lowercase() != "y" && start.to_lowercase() != "n" {
+                println!("Invalid input. Please enter 'y' to start or 'n' to exit: ");
+                start = String::new();
+                io::stdin()
+                    .read_line(&mut start)
+                    .expect("Failed to read line");
+                start = start.trim().to_string();
+            }
 
-    if response.status().is_success() {
-        city
-    } else {
-        println!("City not found. Please enter a valid city name.");
-        get_city()
+            if start.to_lowercase() == "y" {
+                nannou::app(model).update(update).run();
+            } else {
+                println!("Goodbye!");
+            }
+        }
     }
 }
 
-/// The function that returns the filepath of the image of the city.
-/// The function takes in the name of the city as a String and returns the filepath of the image of the city as a String.
-fn get_city_filepath(city: &String) -> String {
-    let mut filepath = "";
-
-    let fix_city = city.to_lowercase();
-    if fix_city == "kyoto" {
-        filepath = "src/assets/kyoto.png";
-    } else if fix_city == "tokyo" {
-        filepath = "src/assets/tokyo.png";
-    } else if fix_city == "london" {
-        filepath = "src/assets/london.png";
-    } else if fix_city == "madrid" {
-        filepath = "src/assets/madrid.png";
-    } else if fix_city == "nashville" {
-        filepath = "src/assets/nashville.png";
-    } else if fix_city == "new york" {
-        filepath = "src/assets/newyork.png";
-    } else {
-        filepath = "src/assets/Empty.png";
+/// The function that prints the error of a command run without a window and exits with a failure status.
+fn exit_on_error(result: Result<(), WeatherError>) {
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
-
-    return filepath.to_string();
 }
 
-/// The function that gets the weather data for the city.
-/// The function takes in the name of the city as a String and returns a compound tuple containing the temperature, the weather id,
-/// and the weather forecast as a String.
-fn get_weather(city: &String) -> ((f64, i64), String) {
-    dotenv().ok();
-    let api_key = env::var("API_KEY").expect("API_KEY must be set");
+/// The function that returns the name of the location given on the command line, used to choose the city
+/// image. Coordinates have no name, so the name in the weather report is used for them instead.
+fn location_name(location: &Location) -> String {
+    match location {
+        Location::City(city) => city.clone(),
+        Location::Coords { .. } => String::new(),
+    }
+}
 
-    let url = format!(
-        "https://api.openweathermap.org/data/2.5/weather?q={}&appid={}&units=metric",
-        city, api_key
-    );
+/// The function that renders the weather scene for the place into an image or animation file, without
+/// opening a window.
+fn render_image(args: &RenderOptions) -> Result<(), WeatherError> {
+    let options = &args.options;
+    let provider = choose_provider(options);
+    let location = options.location.clone().ok_or(WeatherError::NotFound)?;
+    let units = options.units();
+
+    let Lookup {
+        name: city, report, ..
+    } = fetch_city(provider.as_ref(), location_name(&location), location, units)?;
+    let city_image = get_city_filepath(&city);
+    let city_image = Path::new(&city_image);
+    let seed = options.seed().unwrap_or_else(|| scene::scene_seed(&report));
+
+    match (Format::from_path(&args.output), args.seconds) {
+        // Without a length, a .png is a still image rather than a sequence of frames
+        (Some(Format::PngSequence), None) | (None, None) => {
+            render::render_to_file(&report, city_image, &args.output, args.size, seed, units)?
+        }
+        (Some(format), seconds) => {
+            let animation = Animation {
+                seconds: seconds.unwrap_or(Animation::default().seconds),
+                fps: args.fps,
+                size: args.size,
+            };
+            render::render_animation(
+                &report,
+                city_image,
+                &args.output,
+                format,
+                animation,
+                seed,
+                units,
+            )?
+        }
+        (None, Some(_)) => {
+            return Err(WeatherError::Render(format!(
+                "{}: animations can be saved as .gif, .apng or a .png sequence",
+                args.output.display()
+            )))
+        }
+    }
+    println!("Saved the weather in {} to {}", city, args.output.display());
 
-    let client = Client::new();
-    let response = client.get(&url).send().unwrap();
+    Ok(())
+}
 
-    if response.status().is_success() {
-        let json: Value = response.json().unwrap();
-        let temperature = json["main"]["temp"].as_f64().unwrap();
-        let weather = json["weather"][0]["description"]
-            .as_str()
-            .unwrap()
-            .to_string();
-        let weather_id = json["weather"][0]["id"].as_i64().unwrap();
-        let city_name_fixed = json["name"].as_str().unwrap().to_string();
+/// The function that prints the 3-hour forecast for the place, one slot per line.
+fn print_forecast(options: &Options) -> Result<(), WeatherError> {
+    let provider = choose_provider(options);
+    let location = options.location.clone().ok_or(WeatherError::NotFound)?;
+    let units = options.units();
 
+    let reports = provider.forecast(&location)?.reports();
+    if let Some(first) = reports.first() {
+        println!("The forecast for {} from {}:", first.name, provider.name());
+    }
+    for report in &reports {
         println!(
-            "The temperature in {} is {} degrees Celsius and the forecast is: {}",
-            city_name_fixed, temperature, weather
+            "{}  {:>8}  {}",
+            scene::slot_time_label(report.dt, report.timezone),
+            units.format_temperature(report.temperature()),
+            report.descriptions().join(", ")
         );
-        println!("");
-        println!("If you would like to exit the simulation, press 'x' and hit enter.");
-        println!("To see a visualization for a new city, press 'w' and hit enter.");
-
-        return ((temperature, weather_id), weather);
-    } else {
-        ((0.0, 0), "No weather data available".to_string())
     }
-}
 
-/// The function that initializes the model of the application.
-/// The function takes in a reference to the App and returns a Model.
-fn model(app: &App) -> Model {
-    // Initialize the read_flag to false to prevent the background thread from reading stdin initially
-    let read_flag = Arc::new(Mutex::new(false));
-    let (sender, receiver) = mpsc::channel();
-
-    // Spawn a thread to handle user input
-    let read_flag_clone = Arc::clone(&read_flag);
-    thread::spawn(move || {
-        let stdin = io::stdin(); // Get the stdin handle outside of the loop
-        loop {
-            // Only attempt to read if the flag is true
-            if *read_flag_clone.lock().unwrap() {
-                let mut input = String::new();
-                match stdin.read_line(&mut input) {
-                    Ok(_) => {
-                        let input_trimmed = input.trim().to_string();
-                        if input_trimmed.to_lowercase() == "x"
-                            || input_trimmed.to_lowercase() == "w"
-                        {
-                            sender
-                                .send(input_trimmed.clone())
-                                .expect("Failed to send input");
-                            if input_trimmed.to_lowercase() == "x" {
-                                break;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to read line: {}", e);
-                        break;
-                    }
-                }
-            }
-            // Sleep for a short duration to prevent high CPU usage
-            thread::sleep(std::time::Duration::from_millis(100));
-        }
-    });
-
-    // Get the initial city from the user
-    let my_city = get_city();
+    Ok(())
+}
 
-    // Set the read_flag to true now that we have the initial city
-    *read_flag.lock().unwrap() = true;
+/// The function that chooses which weather provider to use, with its reports cached on disk.
+/// The provider is picked by name from `--provider` or else the `WEATHER_PROVIDER` environment variable.
+/// Without a name, OpenWeatherMap is used when an `API_KEY` is set and Open-Meteo, which does not need a
+/// key, is used otherwise.
+fn choose_provider(options: &Options) -> Box<dyn WeatherProvider> {
+    let cache = Cache::in_user_dir(options.cache_ttl());
+    Box::new(CachedProvider::new(
+        provider_by_name(options.provider()),
+        cache,
+    ))
+}
 
-    let filepath = get_city_filepath(&my_city);
-    app.new_window().size(1024, 512).view(view).build().unwrap();
-    let my_texture = wgpu::Texture::from_path(app, filepath).unwrap();
-    let weather = get_weather(&my_city);
+/// The function that returns the provider with the name, or else the provider chosen automatically.
+fn provider_by_name(name: Option<&str>) -> Box<dyn WeatherProvider> {
+    if let Some(provider) = name.and_then(provider::from_name) {
+        return provider;
+    }
 
-    Model {
-        texture: my_texture,
-        city: weather,
-        receiver,
-        read_flag, // Store the flag in the model
+    if env::var("API_KEY").is_ok() {
+        Box::new(OpenWeatherMap::from_env())
+    } else {
+        println!("No API_KEY found, using Open-Meteo which does not need a key.");
+        Box::new(OpenMeteo::default())
     }
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
-    // Check for user input to close the window or get a new city
-    if let Ok(input) = model.receiver.try_recv() {
-        if input.to_lowercase() == "x" {
-            app.set_exit_on_escape(false);
-            println!("");
-            println!("I hope you enjoyed your weather visualization. Goodbye!");
-            app.quit();
-        } else if input.to_lowercase() == "w" {
-            // Set the flag to false to stop reading from stdin in the background thread
-            *model.read_flag.lock().unwrap() = false;
-
-            // Wait for a short duration to ensure the background thread has stopped reading from stdin
-            thread::sleep(std::time::Duration::from_millis(100));
+/// The function that reads one trimmed line from the terminal.
+fn read_line() -> String {
+    let mut line = String::new();
+    io::stdin()
+        .read_line(&mut line)
+        .expect("Failed to read line");
+    line.trim().to_string()
+}
 
-            let new_city = get_city();
-            let new_filepath = get_city_filepath(&new_city);
-            let new_texture = wgpu::Texture::from_path(app, new_filepath).unwrap();
-            let new_weather = get_weather(&new_city);
+/// The function that asks the user to the name of the city they would like the weather for.
+/// The city is looked up with `lookup_city` until a city with weather is found, and the function returns
+/// the city with its weather report.
+/// Errors other than an unknown city, such as a missing API key or a network failure, are returned to the caller.
+fn get_city(provider: &dyn WeatherProvider, units: Units) -> Result<Lookup, WeatherError> {
+    loop {
+        println!("Enter the name of a city you would like the weather for:");
+        let city = read_line();
+        if city.is_empty() {
+            continue;
+        }
 
-            model.texture = new_texture;
-            model.city = new_weather;
+        match lookup_city(provider, &city, units)? {
+            Some(lookup) => return Ok(lookup),
+            None => println!("City not found. Please enter a valid city name."),
+        }
+    }
+}
 
-            // Set the flag back to true to resume reading from stdin in the background thread
-            *model.read_flag.lock().unwrap() = true;
+/// The function that looks up the city the user entered and fetches its weather, with a single weather
+/// request.
+/// The city is looked up with the provider's geocoding, letting the user choose when several places match.
+/// Providers without geocoding fetch the weather by the entered name instead.
+/// The function returns `None` if the city is not found.
+fn lookup_city(
+    provider: &dyn WeatherProvider,
+    city: &str,
+    units: Units,
+) -> Result<Option<Lookup>, WeatherError> {
+    let (name, location) = match provider.geocode(city) {
+        Ok(places) if !places.is_empty() => {
+            let place = choose_place(places);
+            (place.name.clone(), place.location())
         }
+        Ok(_) | Err(WeatherError::NotFound) => return Ok(None),
+        Err(WeatherError::Unsupported(_)) => (city.to_string(), Location::City(city.to_string())),
+        Err(e) => return Err(e),
+    };
+
+    match fetch_city(provider, name, location, units) {
+        Ok(lookup) => Ok(Some(lookup)),
+        Err(WeatherError::NotFound) => Ok(None),
+        Err(e) => Err(e),
     }
 }
 
-/// The function that draws the state of the application to the screen.
-/// The function takes in a reference to the App, a reference to the Model, and a Frame.
-/// It will also analyze the weather data to return the correct weather visualization for the chosen city.
-fn view(app: &App, model: &Model, frame: Frame) {
-    let (temperature, weather) = &model.city.0;
-    let my_temp = get_temp_color(temperature);
+/// The function that asks the user which of the matching places they meant.
+/// A single match is returned without asking.
+fn choose_place(mut places: Vec<Place>) -> Place {
+    if places.len() == 1 {
+        return places.remove(0);
+    }
 
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(my_temp);
-
-    // light thunderstorms
-    if weather == &(200 as i64)
-        || weather == &(201 as i64)
-        || weather == &(210 as i64)
-        || weather == &(230 as i64)
-        || weather == &(231 as i64)
-        || weather == &(232 as i64)
-    {
-        draw_thunderstorm(model, app, my_temp, 50);
-    }
-    // heavy thunderstorms
-    else if weather == &(202 as i64)
-        || weather == &(211 as i64)
-        || weather == &(212 as i64)
-        || weather == &(221 as i64)
-    {
-        draw_thunderstorm(model, app, my_temp, 100);
-    }
-    // drizzle
-    else if weather == &(300 as i64)
-        || weather == &(301 as i64)
-        || weather == &(302 as i64)
-        || weather == &(310 as i64)
-        || weather == &(311 as i64)
-        || weather == &(312 as i64)
-        || weather == &(313 as i64)
-        || weather == &(314 as i64)
-        || weather == &(321 as i64)
-    {
-        draw_rain(model, app, my_temp, 10);
-    }
-    // light to medium rain
-    else if weather == &(500 as i64)
-        || weather == &(501 as i64)
-        || weather == &(520 as i64)
-        || weather == &(521 as i64)
-        || weather == &(531 as i64)
-        || weather == &(511 as i64)
-    {
-        draw_rain(model, app, my_temp, 50);
-    }
-    // heavy rain
-    else if weather == &(502 as i64)
-        || weather == &(503 as i64)
-        || weather == &(504 as i64)
-        || weather == &(522 as i64)
-    {
-        draw_rain(model, app, my_temp, 100);
-    }
-    // light snow
-    else if weather == &(600 as i64)
-        || weather == &(601 as i64)
-        || weather == &(612 as i64)
-        || weather == &(615 as i64)
-        || weather == &(616 as i64)
-        || weather == &(620 as i64)
-        || weather == &(621 as i64)
-        || weather == &(622 as i64)
-    {
-        draw_snow(model, app, my_temp);
-    }
-    // heavy snow
-    else if weather == &(602 as i64) || weather == &(622 as i64) {
-        draw_snow(model, app, my_temp);
-    }
-    // sleet
-    else if weather == &(611 as i64) || weather == &(612 as i64) || weather == &(613 as i64) {
-        draw_sleet(model, app, my_temp);
-    }
-    // mist and haze and fog
-    else if weather == &(701 as i64) || weather == &(721 as i64) {
-        draw_atmospheric_particles(model, app, my_temp, LIGHTGRAY);
-    }
-    // smoke
-    else if weather == &(711 as i64) {
-        draw_atmospheric_particles(model, app, my_temp, DARKGRAY);
-    }
-    // dust
-    else if weather == &(731 as i64) || weather == &(761 as i64) {
-        draw_atmospheric_particles(model, app, my_temp, BURLYWOOD);
-    }
-    // sand
-    else if weather == &(751 as i64) {
-        draw_atmospheric_particles(model, app, my_temp, SANDYBROWN);
-    }
-    // ash
-    else if weather == &(762 as i64) {
-        draw_atmospheric_particles(model, app, my_temp, GRAY);
-    }
-    // squalls
-    else if weather == &(771 as i64) {
-        draw_squalls(model, app, my_temp);
-    }
-    // tornado
-    else if weather == &(781 as i64) {
-        draw_tornado(model, app, my_temp);
-    }
-    // clear sky
-    else if weather == &(800 as i64) {
-        draw_clear_sky(model, app, my_temp);
-    }
-    // few clouds
-    else if weather == &(801 as i64) {
-        draw_overcast(model, app, my_temp, 10, false);
-    }
-    // scattered clouds
-    else if weather == &(802 as i64) {
-        draw_overcast(model, app, my_temp, 50, false);
-    }
-    // broken clouds
-    else if weather == &(803 as i64) {
-        draw_overcast(model, app, my_temp, 75, false);
-    }
-    // overcast clouds
-    else if weather == &(804 as i64) {
-        draw_overcast(model, app, my_temp, 100, false);
-    } else {
-        return;
+    println!("Several places match, which one did you mean?");
+    for (i, place) in places.iter().enumerate() {
+        println!("{}. {}", i + 1, place.label());
     }
 
-    draw.to_frame(app, &frame).unwrap();
+    loop {
+        println!("Enter a number from 1 to {}:", places.len());
+        if let Some(index) = parse_choice(&read_line(), places.len()) {
+            return places.swap_remove(index);
+        }
+    }
 }
 
-/// The function that returns the color of the temperature.
-/// The function takes in a reference to the temperature as a f64 and returns the color of the temperature as an `Srgb<u8>`.
-/// The color of the temperature is determined by the temperature value.
-fn get_temp_color(temperature: &f64) -> Srgb<u8> {
-    let my_temp;
-
-    if temperature > &46.0 {
-        my_temp = BLACK;
-    } else if temperature > &38.0 {
-        my_temp = DARKRED;
-    } else if temperature > &29.0 {
-        my_temp = CRIMSON;
-    } else if temperature > &24.0 {
-        my_temp = ORANGERED;
-    } else if temperature > &16.0 {
-        my_temp = ORANGE;
-    } else if temperature > &10.0 {
-        my_temp = GOLD;
-    } else if temperature > &4.0 {
-        my_temp = LIGHTYELLOW;
-    } else if temperature > &-1.0 {
-        my_temp = PALEGREEN;
-    } else if temperature > &-9.0 {
-        my_temp = POWDERBLUE;
-    } else if temperature > &-18.0 {
-        my_temp = ROYALBLUE;
-    } else if temperature > &-23.0 {
-        my_temp = SLATEBLUE;
-    } else if temperature >= &-29.0 {
-        my_temp = REBECCAPURPLE;
-    } else {
-        my_temp = INDIGO;
+/// The function that turns the number the user entered into an index of the listed places.
+/// The function returns `None` if the input is not one of the listed numbers.
+fn parse_choice(input: &str, n_places: usize) -> Option<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(choice) if (1..=n_places).contains(&choice) => Some(choice - 1),
+        _ => None,
     }
-
-    my_temp
 }
 
-/// The function that draws the weather label on the screen.
-fn draw_weather_label(model: &Model, app: &App, temp: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
-    let win = app.window_rect();
-
-    let forecast = format!("Forecast: {}", &model.city.1);
-    let forecast_str: &str = &forecast;
+/// The function that returns the filepath of the image of the city.
+/// The function takes in the name of the city as a String and returns the filepath of the image of the city as a String.
+fn get_city_filepath(city: &str) -> String {
+    let fix_city = city.to_lowercase();
+    let filepath = if fix_city == "kyoto" {
+        "src/assets/kyoto.png"
+    } else if fix_city == "tokyo" {
+        "src/assets/tokyo.png"
+    } else if fix_city == "london" {
+        "src/assets/london.png"
+    } else if fix_city == "madrid" {
+        "src/assets/madrid.png"
+    } else if fix_city == "nashville" {
+        "src/assets/nashville.png"
+    } else if fix_city == "new york" {
+        "src/assets/newyork.png"
+    } else {
+        "src/assets/Empty.png"
+    };
 
-    draw.text(forecast_str)
-        .x_y(-300.0, win.top() - 400.0)
-        .color(BLACK)
-        .font_size(24);
+    filepath.to_string()
+}
 
-    let number_string: String = (&model.city.0 .0).to_string();
-    let number_str: &str = &number_string;
-    let temp_str = format!("Temperature: {} °C", number_str);
+/// The function that gets the weather data for the location.
+/// The function returns the full weather report for the location, or the error that prevented the report
+/// from being fetched.
+fn get_weather(
+    provider: &dyn WeatherProvider,
+    location: &Location,
+    units: Units,
+) -> Result<WeatherReport, WeatherError> {
+    let report = provider.current(location)?;
+
+    println!(
+        "The temperature in {} is {} and the forecast is: {}",
+        report.name,
+        units.format_temperature(report.temperature()),
+        report.description()
+    );
+    if let Some(stale) = scene::stale_label(&report) {
+        println!(
+            "Could not reach {}, showing the last report ({}).",
+            provider.name(),
+            stale
+        );
+    }
 
-    draw.text(&temp_str)
-        .x_y(-300.0, win.top() - 460.0)
-        .color(BLACK)
-        .font_size(24);
+    Ok(report)
 }
 
-/// The function that draws the weather visualization for different heaviness of rain.
-fn draw_rain(model: &Model, app: &App, temp: Srgb<u8>, speed: i32) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
-
-    draw_overcast(model, app, temp, speed, true);
+/// A city with its weather, the result of looking up a city.
+struct Lookup {
+    /// The name of the city, used to choose the city image.
+    name: String,
+    location: Location,
+    report: WeatherReport,
+}
 
-    let win = app.window_rect();
-    let n_drops = speed;
-    for _ in 0..n_drops {
-        let x = random_range(win.left(), win.right());
-        let y = random_range(win.top() - 200.0, win.bottom());
-        draw.ellipse().xy(pt2(x, y)).radius(10.0).color(BLUE);
-    }
+/// The function that fetches the weather for the named place.
+/// Places without a name, such as coordinates, are named after the weather report.
+fn fetch_city(
+    provider: &dyn WeatherProvider,
+    name: String,
+    location: Location,
+    units: Units,
+) -> Result<Lookup, WeatherError> {
+    let report = get_weather(provider, &location, units)?;
+    let name = if name.is_empty() {
+        report.name.clone()
+    } else {
+        name
+    };
+
+    Ok(Lookup {
+        name,
+        location,
+        report,
+    })
 }
 
-/// The function that draws the weather visualization for thunderstorms.
-fn draw_thunderstorm(model: &Model, app: &App, temp: Srgb<u8>, speed: i32) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+/// The function that prints how looking up a city went, with the instructions for the window.
+/// The function returns the filepath of the image of the city.
+/// Errors are printed to the terminal, so they can also be shown in the window.
+fn print_lookup(provider: &dyn WeatherProvider, lookup: &Result<Lookup, WeatherError>) -> String {
+    let filepath = match lookup {
+        Ok(lookup) => get_city_filepath(&lookup.name),
+        Err(e) => {
+            println!("Could not get the weather from {}: {}", provider.name(), e);
+            get_city_filepath("")
+        }
+    };
+    println!(
+        "{} requests have been sent to the weather APIs so far.",
+        provider::request_count()
+    );
+    println!();
+    println!("To search for a new city, press 'w' in the window, type the city and press enter.");
+    println!("If you would like to exit the simulation, press Escape in the window.");
+    println!("To see the 3-hour forecast, press 'f' in the window and scrub through it with the arrow keys.");
 
-    let win = app.window_rect();
+    filepath
+}
 
-    draw_overcast(model, app, temp, speed, true);
-    draw_rain(model, app, temp, speed);
+/// The function that fetches the 3-hourly forecast for the place of the current weather report.
+/// Reports with coordinates are looked up by coordinates, others by their name.
+/// With `refresh` the forecast is fetched again even when a recent one is cached.
+fn get_forecast(
+    provider: &dyn WeatherProvider,
+    report: &WeatherReport,
+    refresh: bool,
+) -> Result<Vec<WeatherReport>, WeatherError> {
+    let location = if report.coord == Coord::default() {
+        Location::City(report.name.clone())
+    } else {
+        Location::Coords {
+            lat: report.coord.lat,
+            lon: report.coord.lon,
+        }
+    };
 
-    for _ in 0..10 {
-        let start_x = random_range(win.left(), win.right());
-        let start_y = random_range(win.top(), win.bottom());
-        let end_x = random_range(win.left(), win.right());
-        let end_y = random_range(win.top(), win.bottom());
-        draw.polyline()
-            .weight(2.0)
-            .points(vec![pt2(start_x, start_y), pt2(end_x, end_y)])
-            .color(YELLOW);
+    let forecast = if refresh {
+        provider.refresh_forecast(&location)?
+    } else {
+        provider.forecast(&location)?
+    };
+    let reports = forecast.reports();
+    if reports.is_empty() {
+        return Err(WeatherError::MissingField("list"));
     }
-}
 
-/// The function that draws the weather visualization for snow.
-fn draw_snow(model: &Model, app: &App, temp: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+    Ok(reports)
+}
 
-    draw_weather_label(model, app, temp);
+/// The function that asks the worker for the forecast of the current city if it has not been fetched yet.
+/// While new weather is loading, the forecast is asked for once the weather arrives.
+/// With `refresh` the forecast is fetched again even when a recent one is cached, as in watch mode.
+fn load_forecast(model: &mut Model, refresh: bool) {
+    if model.forecast.is_some() || model.forecast_loading || model.loading.is_some() {
+        return;
+    }
 
-    let win = app.window_rect();
-    let n_drops = 5;
-    for _ in 0..n_drops {
-        let x = random_range(win.left(), win.right());
-        let y = random_range(win.top() - 200.0, win.bottom());
-        draw.ellipse().xy(pt2(x, y)).radius(10.0).color(WHITE);
+    if let Ok(report) = &model.report {
+        let report = report.clone();
+        model.forecast_loading = true;
+        model.worker.run(move |provider| {
+            let forecast = get_forecast(provider, &report, refresh);
+            if let Err(e) = &forecast {
+                println!("Could not get the forecast from {}: {}", provider.name(), e);
+            }
+            Message::Forecast(forecast)
+        });
     }
 }
 
-/// The function that draws the weather visualization for sleet.
-fn draw_sleet(model: &Model, app: &App, temp: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+/// The function that initializes the model of the application.
+/// The function takes in a reference to the App and returns a Model.
+fn model(app: &App) -> Model {
+    // Get the initial city from the user
+    dotenv().ok();
+    let options = OPTIONS.get().cloned().unwrap_or_default();
+    let units = options.units();
+    let provider = choose_provider(&options);
+    let lookup = match &options.location {
+        Some(location) => fetch_city(
+            provider.as_ref(),
+            location_name(location),
+            location.clone(),
+            units,
+        ),
+        None => get_city(provider.as_ref(), units),
+    };
+    let filepath = print_lookup(provider.as_ref(), &lookup);
+    let mut search = SearchBox::default();
+    let mut place = options.location.clone();
+    if let Ok(lookup) = &lookup {
+        search.remember(Suggestion {
+            label: lookup.name.clone(),
+            name: lookup.name.clone(),
+            location: lookup.location.clone(),
+        });
+        place = Some(lookup.location.clone());
+    }
+    let weather = lookup.map(|lookup| lookup.report);
+
+    app.new_window()
+        .size(1024, 512)
+        .view(view)
+        .key_pressed(key_pressed)
+        .received_character(received_character)
+        .build()
+        .unwrap();
+    // Escape closes the search box, and only quits when the search box is closed
+    app.set_exit_on_escape(false);
+    let my_texture = wgpu::Texture::from_path(app, filepath).unwrap();
 
-    draw_rain(model, app, temp, 10);
+    let mut model = Model {
+        texture: my_texture,
+        report: weather,
+        worker: Worker::spawn(provider),
+        loading: None,
+        search,
+        forecast_mode: false,
+        forecast: None,
+        forecast_loading: false,
+        selected: 0,
+        scene: SceneState::default(),
+        seed: options.seed(),
+        units,
+        rng: StdRng::seed_from_u64(0),
+        frame_seed: 0,
+        place,
+        refresher: options.watch().map(|watch| Refresher::new(watch, app.time)),
+        refreshing: false,
+        fade: None,
+    };
+    reset_scene(&mut model, app.window_rect());
+
+    model
+}
 
-    let win = app.window_rect();
-    let n_drops = 100;
-    for _ in 0..n_drops {
-        let x = random_range(win.left(), win.right());
-        let y = random_range(win.top() - 200.0, win.bottom());
-        draw.ellipse().xy(pt2(x, y)).radius(10.0).color(WHITE);
+/// The function that returns the report shown in the window: the selected forecast slot in forecast mode
+/// and the current weather otherwise.
+fn shown_report(model: &Model) -> Result<&WeatherReport, &WeatherError> {
+    match (&model.report, &model.forecast) {
+        (Err(e), _) => Err(e),
+        (Ok(_), Some(Ok(reports))) if model.forecast_mode => Ok(&reports[model.selected]),
+        (Ok(_), Some(Err(e))) if model.forecast_mode => Err(e),
+        (Ok(report), _) => Ok(report),
     }
 }
 
-/// The function that draws the weather visualization for different cloud coverages.
-fn draw_overcast(model: &Model, app: &App, temp: Srgb<u8>, speed: i32, rain: bool) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
-
-    let win = app.window_rect();
-
-    draw_weather_label(model, app, temp);
+/// The function that builds new particles and clouds for the shown report, called whenever the shown report
+/// changes. The random numbers are seeded again, from the configured seed or from the report, so the same
+/// report always plays out the same way.
+fn reset_scene(model: &mut Model, bounds: Rect) {
+    let (state, rng) = match shown_report(model) {
+        Ok(report) => {
+            let seed = model.seed.unwrap_or_else(|| scene::scene_seed(report));
+            SceneState::seeded(report, bounds, seed)
+        }
+        Err(_) => (
+            SceneState::default(),
+            StdRng::seed_from_u64(model.seed.unwrap_or_default()),
+        ),
+    };
+    model.rng = rng;
+    model.scene = state;
+}
 
-    let cloud_color;
-    if rain {
-        cloud_color = DIMGRAY;
-    } else {
-        cloud_color = LIGHTGRAY;
-        if speed < 50 {
-            draw_clear_sky(model, app, temp);
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.frame_seed = scene::frame_seed(&mut model.rng);
+    model
+        .scene
+        .update(update.since_last.as_secs_f32(), app.window_rect());
+    if let Some(fade) = &mut model.fade {
+        fade.scene
+            .update(update.since_last.as_secs_f32(), app.window_rect());
+        if fade.progress(app.time) >= 1.0 {
+            model.fade = None;
         }
     }
 
-    let n_clouds = speed;
-    for _ in 0..n_clouds {
-        let x = random_range(win.left(), win.right());
-        let y = random_range(win.top(), win.bottom() + 300.0);
-        draw.ellipse().color(cloud_color).w(90.0).h(60.0).x_y(x, y);
-        draw.ellipse()
-            .color(cloud_color)
-            .w(90.0)
-            .h(60.0)
-            .x_y(x, y + 50.0);
-        draw.ellipse()
-            .color(cloud_color)
-            .w(90.0)
-            .h(60.0)
-            .x_y(x - 50.0, y);
-        draw.ellipse()
-            .color(cloud_color)
-            .w(90.0)
-            .h(60.0)
-            .x_y(x + -0.0, y + 25.0);
-        draw.ellipse()
-            .color(cloud_color)
-            .w(90.0)
-            .h(60.0)
-            .x_y(x + 50.0, y + 25.0);
-    }
-}
-
-/// The function that draws the weather visualization for different atmospheric particles.
-fn draw_atmospheric_particles(model: &Model, app: &App, temp: Srgb<u8>, weather_cond: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+    // Fetch the weather of the shown place again in watch mode
+    if let (Some(refresher), Some(location)) = (&model.refresher, &model.place) {
+        if refresher.is_due(app.time) && !model.refreshing && model.loading.is_none() {
+            let location = location.clone();
+            model.refreshing = true;
+            model.worker.run(move |provider| {
+                let report = provider.refresh(&location);
+                Message::Refresh {
+                    location,
+                    report: Box::new(report),
+                }
+            });
+        }
+    }
 
-    draw_weather_label(model, app, temp);
+    // Look up the places matching the search once the user stops typing
+    if let Some(query) = model.search.take_lookup(app.time) {
+        model.worker.run(move |provider| {
+            let places = provider.geocode(&query).unwrap_or_default();
+            Message::Places { query, places }
+        });
+    }
 
-    let win = app.window_rect();
-    let n_drops = 2000;
-    for _ in 0..n_drops {
-        let x = random_range(win.left(), win.right());
-        let y = random_range(win.top(), win.bottom());
-        draw.ellipse().xy(pt2(x, y)).radius(1.0).color(weather_cond);
+    while let Some(message) = model.worker.try_recv() {
+        receive(app, model, message);
     }
 }
 
-/// The function that draws the weather visualization for squalls.
-fn draw_squalls(model: &Model, app: &App, temp: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+/// The function that shows a result the worker sent back.
+fn receive(app: &App, model: &mut Model, message: Message) {
+    match message {
+        Message::Weather {
+            suggestion,
+            filepath,
+            report,
+        } => {
+            // A place chosen while this one was loading is still on its way
+            if matches!(&model.loading, Some((label, _)) if *label == suggestion.label) {
+                model.loading = None;
+            }
+            if report.is_ok() {
+                model.place = Some(suggestion.location.clone());
+                model.search.remember(suggestion);
+                if let Some(refresher) = &mut model.refresher {
+                    refresher.succeeded(app.time);
+                }
+            } else {
+                // The error replaces the scene, so no place is shown or refreshed any more
+                model.place = None;
+            }
+            model.fade = None;
+            model.texture = wgpu::Texture::from_path(app, filepath).unwrap();
+            model.report = *report;
+            model.forecast = None;
+            if model.forecast_mode {
+                load_forecast(model, false);
+            }
+        }
+        Message::Forecast(forecast) => {
+            model.forecast = Some(forecast);
+            model.forecast_loading = false;
+            model.selected = 0;
+        }
+        Message::Places { query, places } => {
+            model.search.set_places(&query, &places);
+            return;
+        }
+        Message::Refresh { location, report } => {
+            model.refreshing = false;
+            // The refresh of a place the user has since moved away from
+            if model.place.as_ref() != Some(&location) {
+                return;
+            }
+            if let Some(refresher) = &mut model.refresher {
+                match &*report {
+                    Ok(report) if report.stale_since.is_none() => refresher.succeeded(app.time),
+                    Ok(_) => refresher.failed(app.time),
+                    Err(e) => {
+                        refresher.failed(app.time);
+                        println!(
+                            "Could not refresh the weather, trying again in {:.0} seconds: {}",
+                            refresher.delay(),
+                            e
+                        );
+                    }
+                }
+            }
+            // A failed refresh keeps the last weather on the screen
+            if let Ok(report) = *report {
+                show_refresh(app, model, &location, report);
+            }
+            return;
+        }
+    }
+    reset_scene(model, app.window_rect());
+}
 
-    let win = app.window_rect();
-    draw_weather_label(model, app, temp);
+/// The function that shows the weather of the shown place fetched again in watch mode.
+/// When the conditions changed, the old scene fades into the new one. Otherwise the scene keeps playing
+/// and only the label changes. When the place could not be shown before, its city image is loaded now.
+fn show_refresh(app: &App, model: &mut Model, location: &Location, report: WeatherReport) {
+    let layers = |report: &WeatherReport| Layers::from_conditions(&report.conditions());
+    let changed = match &model.report {
+        Ok(shown) => layers(shown) != layers(&report),
+        Err(_) => {
+            let mut name = location_name(location);
+            if name.is_empty() {
+                name = report.name.clone();
+            }
+            model.texture = wgpu::Texture::from_path(app, get_city_filepath(&name)).unwrap();
+            true
+        }
+    };
+    let previous = std::mem::replace(&mut model.report, Ok(report));
+    model.forecast = None;
+    // In forecast mode the forecast is fetched again and its slots stay on the screen
+    if model.forecast_mode && previous.is_ok() {
+        load_forecast(model, true);
+        return;
+    }
+    if !changed {
+        return;
+    }
 
-    // Draw wind lines
-    let n_lines = 50;
-    for _ in 0..n_lines {
-        let start_x = random_range(win.left(), win.right());
-        let start_y = random_range(win.top(), win.bottom());
-        let end_x = start_x + random_range(50.0, 150.0);
-        let end_y = start_y + random_range(-20.0, 20.0);
-        draw.line()
-            .start(pt2(start_x, start_y))
-            .end(pt2(end_x, end_y))
-            .weight(2.0)
-            .color(GAINSBORO);
+    let fade = model
+        .refresher
+        .as_ref()
+        .map_or(0.0, |refresher| refresher.watch.fade);
+    if let (Ok(previous), true) = (previous, fade > 0.0) {
+        let window = app.main_window();
+        let win = app.window_rect();
+        let layer = wgpu::TextureBuilder::new()
+            .size([win.w() as u32, win.h() as u32])
+            .format(Frame::TEXTURE_FORMAT)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .build(window.device());
+        let renderer = draw::RendererBuilder::new()
+            .build_from_texture_descriptor(window.device(), layer.descriptor());
+        model.fade = Some(Fade {
+            report: previous,
+            scene: std::mem::take(&mut model.scene),
+            started: app.time,
+            seconds: fade,
+            layer,
+            renderer: RefCell::new(renderer),
+        });
     }
+    reset_scene(model, app.window_rect());
 }
 
-/// The function that draws the weather visualization for tornado.
-fn draw_tornado(model: &Model, app: &App, temp: Srgb<u8>) {
-    let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
+/// The function that asks the worker for the weather of the place chosen in the search box.
+/// The current scene keeps playing until the weather arrives.
+fn load_city(app: &App, model: &mut Model, suggestion: Suggestion) {
+    let units = model.units;
+    model.loading = Some((suggestion.label.clone(), app.time));
+    model.worker.run(move |provider| {
+        let lookup = fetch_city(
+            provider,
+            suggestion.name.clone(),
+            suggestion.location.clone(),
+            units,
+        );
+        let filepath = print_lookup(provider, &lookup);
+        Message::Weather {
+            suggestion,
+            filepath,
+            report: Box::new(lookup.map(|lookup| lookup.report)),
+        }
+    });
+}
 
-    let win = app.window_rect();
+/// The function that handles the characters typed in the window.
+/// 'w' opens the search box, and while it is open the characters are added to the query.
+fn received_character(app: &App, model: &mut Model, c: char) {
+    if model.search.open {
+        model.search.type_char(c, app.time);
+    } else if c == 'w' || c == 'W' {
+        model.search.open();
+    }
+}
 
-    draw_squalls(model, app, temp);
+/// The function that handles the keys pressed in the window.
+/// While the search box is open, Enter loads the chosen place, Escape closes the box and the arrow keys
+/// choose a suggestion. Otherwise 'f' toggles forecast mode, the left and right arrow keys scrub through
+/// the forecast slots and Escape quits.
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.search.open {
+        match key {
+            Key::Return | Key::NumpadEnter => {
+                if let Some(suggestion) = model.search.choice() {
+                    model.search.close();
+                    load_city(app, model, suggestion);
+                }
+            }
+            Key::Escape => model.search.close(),
+            Key::Back => model.search.backspace(app.time),
+            Key::Up => model.search.move_selection(-1),
+            Key::Down | Key::Tab => model.search.move_selection(1),
+            _ => {}
+        }
+        return;
+    }
 
-    // Draw the funnel shape of the tornado
-    let funnel_height = 300.0;
-    let funnel_width = 200.0;
-    let funnel_steps = 50;
-    let step_height = funnel_height / funnel_steps as f32;
-    let step_width = funnel_width / funnel_steps as f32;
+    let selected = model.selected;
+    match key {
+        Key::Escape => {
+            println!();
+            println!("I hope you enjoyed your weather visualization. Goodbye!");
+            app.quit();
+            return;
+        }
+        Key::F => {
+            model.forecast_mode = !model.forecast_mode;
+            if model.forecast_mode {
+                load_forecast(model, false);
+            }
+        }
+        Key::Left if model.forecast_mode => {
+            model.selected = model.selected.saturating_sub(1);
+        }
+        Key::Right if model.forecast_mode => {
+            if let Some(Ok(reports)) = &model.forecast {
+                model.selected = (model.selected + 1).min(reports.len() - 1);
+            }
+        }
+        _ => return,
+    }
 
-    for i in 0..funnel_steps {
-        let y = (win.top() - 100.0) - i as f32 * step_height;
-        let width = funnel_width - i as f32 * step_width;
-        draw.ellipse()
-            .x_y(0.0, y)
-            .w_h(width, step_height)
-            .color(DIMGRAY)
-            .stroke(BLACK)
-            .stroke_weight(1.0);
+    if key == Key::F || model.selected != selected {
+        reset_scene(model, app.window_rect());
     }
 }
 
-/// The function that draws the weather visualization for a sun in the sky.
-fn draw_clear_sky(model: &Model, app: &App, temp: Srgb<u8>) {
+/// The function that draws the state of the application to the screen.
+/// The function takes in a reference to the App, a reference to the Model, and a Frame.
+/// Every condition in the weather report is layered into one scene for the chosen city.
+/// In forecast mode the selected forecast slot is drawn instead, with the forecast timeline along the bottom.
+fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
-    draw.texture(&model.texture);
-    draw.background().color(temp);
-
     let win = app.window_rect();
 
-    draw_weather_label(model, app, temp);
-
-    // Draw the sun
-    let sun_radius = 50.0;
-    draw.ellipse()
-        .x_y(0.0, win.top() - sun_radius - 50.0)
-        .w_h(sun_radius * 2.0, sun_radius * 2.0)
-        .color(YELLOW);
-
-    // Draw sun rays
-    let n_rays = 20;
-    let ray_length = 100.0;
-    for i in 0..n_rays {
-        let angle = i as f32 * (360.0 / n_rays as f32);
-        let (sin, cos) = angle.to_radians().sin_cos();
-        let start_x = cos * sun_radius;
-        let start_y = win.top() - sun_radius - 50.0 + sin * sun_radius;
-        let end_x = cos * (sun_radius + ray_length);
-        let end_y = win.top() - sun_radius - 50.0 + sin * (sun_radius + ray_length);
-        draw.line()
-            .start(pt2(start_x, start_y))
-            .end(pt2(end_x, end_y))
-            .weight(2.0)
-            .color(YELLOW);
+    match shown_report(model) {
+        Ok(report) => {
+            draw_weather(&draw, win, model, report, &model.scene, app.time);
+            if let (true, Some(Ok(reports))) = (model.forecast_mode, &model.forecast) {
+                scene::draw_timeline(&draw, win, reports, model.selected);
+            }
+        }
+        Err(e) => scene::draw_error(&draw, win, &model.texture, e),
+    }
+    // During a fade the old scene is laid over the new one, more see-through every frame
+    if let Some(fade) = &model.fade {
+        let old = Draw::new();
+        draw_weather(&old, win, model, &fade.report, &fade.scene, app.time);
+        scene::draw_fade(&old, win, fade.progress(app.time));
+        fade.renderer.borrow_mut().render_to_texture(
+            frame.device_queue_pair().device(),
+            &mut frame.command_encoder(),
+            &old,
+            &fade.layer,
+        );
+        draw.texture(&fade.layer).wh(win.wh());
+    }
+    if let Some((label, started)) = &model.loading {
+        let text = format!("Loading {}...", label);
+        scene::draw_loading(&draw, win, &text, app.time - started);
+    } else if model.forecast_mode && model.forecast_loading {
+        scene::draw_loading(&draw, win, "Loading the forecast...", app.time);
     }
+    model.search.draw(&draw, win, app.time);
+
+    draw.to_frame(app, &frame).unwrap();
+}
+
+/// The function that draws the scene of the report with its particles and clouds and the weather label.
+fn draw_weather(
+    draw: &Draw,
+    win: Rect,
+    model: &Model,
+    report: &WeatherReport,
+    state: &SceneState,
+    time: f32,
+) {
+    scene::draw_scene(
+        draw,
+        win,
+        &model.texture,
+        report,
+        state,
+        time,
+        &mut StdRng::seed_from_u64(model.frame_seed),
+    );
+    scene::draw_weather_label(draw, win, report, model.units);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockito::mock;
-    use std::env;
-    use std::sync::Once;
+    use mockito::{mock, Matcher};
+    use std::sync::{Mutex, MutexGuard, Once};
 
     // Ensure that the .env file is loaded only once
     static INIT: Once = Once::new();
 
+    // The tests that send requests run one at a time, so the request count only counts one test
+    static REQUESTS: Mutex<()> = Mutex::new(());
+
     fn setup() {
         INIT.call_once(|| {
             dotenv().ok();
         });
     }
 
+    fn sending_requests() -> MutexGuard<'static, ()> {
+        REQUESTS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     #[test]
     fn test_get_city_filepath() {
-        assert_eq!(
-            get_city_filepath(&"Kyoto".to_string()),
-            "src/assets/kyoto.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"Tokyo".to_string()),
-            "src/assets/tokyo.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"London".to_string()),
-            "src/assets/london.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"Madrid".to_string()),
-            "src/assets/madrid.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"Nashville".to_string()),
-            "src/assets/nashville.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"New York".to_string()),
-            "src/assets/newyork.png"
-        );
-        assert_eq!(
-            get_city_filepath(&"Unknown".to_string()),
-            "src/assets/Empty.png"
-        );
+        assert_eq!(get_city_filepath("Kyoto"), "src/assets/kyoto.png");
+        assert_eq!(get_city_filepath("Tokyo"), "src/assets/tokyo.png");
+        assert_eq!(get_city_filepath("London"), "src/assets/london.png");
+        assert_eq!(get_city_filepath("Madrid"), "src/assets/madrid.png");
+        assert_eq!(get_city_filepath("Nashville"), "src/assets/nashville.png");
+        assert_eq!(get_city_filepath("New York"), "src/assets/newyork.png");
+        assert_eq!(get_city_filepath("Unknown"), "src/assets/Empty.png");
     }
 
     #[test]
-    fn test_get_temp_color() {
-        assert_eq!(get_temp_color(&50.0), BLACK);
-        assert_eq!(get_temp_color(&40.0), DARKRED);
-        assert_eq!(get_temp_color(&30.0), CRIMSON);
-        assert_eq!(get_temp_color(&25.0), ORANGERED);
-        assert_eq!(get_temp_color(&20.0), ORANGE);
-        assert_eq!(get_temp_color(&15.0), GOLD);
-        assert_eq!(get_temp_color(&5.0), LIGHTYELLOW);
-        assert_eq!(get_temp_color(&0.0), PALEGREEN);
-        assert_eq!(get_temp_color(&-5.0), POWDERBLUE);
-        assert_eq!(get_temp_color(&-15.0), ROYALBLUE);
-        assert_eq!(get_temp_color(&-20.0), SLATEBLUE);
-        assert_eq!(get_temp_color(&-25.0), REBECCAPURPLE);
-        assert_eq!(get_temp_color(&-30.0), INDIGO);
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("1", 3), Some(0));
+        assert_eq!(parse_choice(" 3 ", 3), Some(2));
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("Paris", 3), None);
     }
 
     #[test]
     fn test_get_weather() {
         setup();
+        let _requests = sending_requests();
 
         let _m = mock(
             "GET",
@@ -749,32 +982,85 @@ mod tests {
         )
         .create();
 
-        let city = "London".to_string();
-        let url = format!(
-            "{}/data/2.5/weather?q={}&appid=mock_api_key&units=metric",
-            &mockito::server_url(),
-            city
-        );
+        let provider =
+            OpenWeatherMap::new(Some("mock_api_key".to_string()), &mockito::server_url());
+        let report = get_weather(
+            &provider,
+            &Location::City("London".to_string()),
+            Units::Metric,
+        )
+        .unwrap();
 
-        let client = Client::new();
-        let response = client.get(&url).send().unwrap();
-
-        if response.status().is_success() {
-            let json: Value = response.json().unwrap();
-            let temperature = json["main"]["temp"].as_f64().unwrap();
-            let weather = json["weather"][0]["description"]
-                .as_str()
-                .unwrap()
-                .to_string();
-            let weather_id = json["weather"][0]["id"].as_i64().unwrap();
-            let city_name_fixed = json["name"].as_str().unwrap().to_string();
-
-            assert_eq!(city_name_fixed, "London");
-            assert!(temperature > -50.0 && temperature < 50.0);
-            assert!(!weather.is_empty());
-            assert!(weather_id > 0);
-        } else {
-            panic!("Failed to get weather data");
-        }
+        assert_eq!(report.name, "London");
+        assert!(report.temperature() > -50.0 && report.temperature() < 50.0);
+        assert!(!report.description().is_empty());
+        assert!(report.weather_id() > 0);
+    }
+
+    #[test]
+    fn test_lookup_city_fetches_the_weather_once() {
+        let _requests = sending_requests();
+        let geocode = mock("GET", "/geo/1.0/direct")
+            .match_query(Matcher::UrlEncoded("q".into(), "Kyoto".into()))
+            .with_status(200)
+            .with_body(r#"[{"name": "Kyoto", "lat": 35.0116, "lon": 135.7681, "country": "JP"}]"#)
+            .expect(1)
+            .create();
+        let weather = mock("GET", "/data/2.5/weather")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("lat".into(), "35.0116".into()),
+                Matcher::UrlEncoded("lon".into(), "135.7681".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"main": {"temp": 18.0}, "weather": [{"id": 800, "description": "clear sky"}], "name": "Kyoto"}"#)
+            .expect(1)
+            .create();
+
+        let provider =
+            OpenWeatherMap::new(Some("mock_api_key".to_string()), &mockito::server_url());
+        let requests = provider::request_count();
+        let lookup = lookup_city(&provider, "Kyoto", Units::Metric)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lookup.name, "Kyoto");
+        assert_eq!(lookup.report.weather_id(), 800);
+        assert_eq!(provider::request_count(), requests + 2);
+        geocode.assert();
+        weather.assert();
+    }
+
+    #[test]
+    fn test_get_forecast() {
+        let _requests = sending_requests();
+        let _m = mock("GET", "/data/2.5/forecast")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("lat".into(), "40.4165".into()),
+                Matcher::UrlEncoded("lon".into(), "-3.7026".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "list": [
+                        {"dt": 1700006400, "main": {"temp": 12.0}, "weather": [{"id": 800, "description": "clear sky"}]},
+                        {"dt": 1700017200, "main": {"temp": 9.0}, "weather": [{"id": 500, "description": "light rain"}]}
+                    ],
+                    "city": {"name": "Madrid", "coord": {"lat": 40.4165, "lon": -3.7026}, "country": "ES", "timezone": 3600}
+                }"#,
+            )
+            .create();
+
+        let provider =
+            OpenWeatherMap::new(Some("mock_api_key".to_string()), &mockito::server_url());
+        let current: WeatherReport = serde_json::from_str(
+            r#"{"coord": {"lat": 40.4165, "lon": -3.7026}, "main": {"temp": 14.0}, "name": "Madrid"}"#,
+        )
+        .unwrap();
+        let reports = get_forecast(&provider, &current, false).unwrap();
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].name, "Madrid");
+        assert_eq!(reports[1].timezone, 3600);
+        assert_eq!(reports[1].weather_id(), 500);
     }
 }
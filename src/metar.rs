@@ -0,0 +1,715 @@
+//! Decoding of METAR aviation weather reports.
+//!
+//! TAF forecasts are rejected, since they do not report the temperature the scenes are colored by.
+
+use crate::condition::Intensity;
+use crate::error::WeatherError;
+use crate::report::{Clouds, Coord, MainReadings, Sys, WeatherCondition, WeatherReport, Wind};
+
+/// Knots to meters per second.
+const KNOTS_TO_MPS: f64 = 0.514444;
+
+/// Statute miles to meters.
+const MILES_TO_METERS: f64 = 1609.344;
+
+/// A decoded METAR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metar {
+    pub station: String,
+    /// The day of the month and the UTC hour and minute of the observation.
+    pub time: Option<(u32, u32, u32)>,
+    pub wind: Option<MetarWind>,
+    /// The prevailing visibility in meters.
+    pub visibility: Option<f64>,
+    pub weather: Vec<PresentWeather>,
+    pub clouds: Vec<CloudLayer>,
+    /// The temperature and dewpoint in degrees Celsius.
+    pub temperature: Option<f64>,
+    pub dewpoint: Option<f64>,
+    /// The altimeter setting in hectopascals.
+    pub pressure: Option<f64>,
+}
+
+/// The wind group, such as `20012G20KT`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetarWind {
+    /// The direction the wind blows from in degrees, or `None` when it is variable.
+    pub direction: Option<f64>,
+    /// The speed and gust in meters per second.
+    pub speed: f64,
+    pub gust: Option<f64>,
+}
+
+/// A present weather group, such as `+TSRA`, `BR` or `-SHSN`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresentWeather {
+    /// How strong the group is, from its `-` or `+` prefix.
+    pub intensity: Intensity,
+    pub vicinity: bool,
+    /// The descriptor, such as `TS`, `SH` or `FZ`.
+    pub descriptor: Option<String>,
+    /// The phenomena in the order they were reported, such as `RA` and `SN`.
+    pub phenomena: Vec<String>,
+}
+
+/// A cloud layer, such as `BKN035`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudLayer {
+    /// The sky cover code: `FEW`, `SCT`, `BKN`, `OVC` or `VV` for a vertical visibility.
+    pub cover: String,
+    /// The height of the cloud base in feet.
+    pub height: Option<u32>,
+}
+
+const DESCRIPTORS: [&str; 8] = ["MI", "PR", "BC", "DR", "BL", "SH", "TS", "FZ"];
+
+const PHENOMENA: [&str; 22] = [
+    "DZ", "RA", "SN", "SG", "IC", "PL", "GR", "GS", "UP", "BR", "FG", "FU", "VA", "DU", "SA", "HZ",
+    "PY", "PO", "SQ", "FC", "SS", "DS",
+];
+
+/// The function that decodes a METAR such as `KBNA 142153Z 20012G20KT 8SM -TSRA BKN035 22/18 A2992`.
+/// Remarks after `RMK` and trend groups such as `TEMPO` and `BECMG` are ignored.
+/// A TAF, recognized by its `TAF` prefix or its validity period such as `1418/1518`, is not supported.
+pub fn parse(text: &str) -> Result<Metar, WeatherError> {
+    let invalid =
+        |reason: &str| WeatherError::InvalidMetar(format!("{}: '{}'", reason, text.trim()));
+    let mut tokens = text
+        .split_whitespace()
+        .map(|token| token.trim_end_matches('='))
+        .peekable();
+
+    while let Some(&token) = tokens.peek() {
+        if token == "TAF" {
+            return Err(WeatherError::Unsupported(
+                "TAF forecasts, only METAR observations",
+            ));
+        }
+        if matches!(token, "METAR" | "SPECI" | "AMD" | "COR") {
+            tokens.next();
+        } else {
+            break;
+        }
+    }
+
+    let station = tokens.next().ok_or_else(|| invalid("empty report"))?;
+    if station.len() != 4 || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(invalid("expected a four letter station code"));
+    }
+
+    let mut metar = Metar {
+        station: station.to_uppercase(),
+        time: None,
+        wind: None,
+        visibility: None,
+        weather: Vec::new(),
+        clouds: Vec::new(),
+        temperature: None,
+        dewpoint: None,
+        pressure: None,
+    };
+
+    let mut whole_miles: Option<f64> = None;
+    for token in tokens {
+        if matches!(token, "RMK" | "TEMPO" | "BECMG" | "NOSIG") {
+            break;
+        }
+        if is_validity_period(token) {
+            return Err(WeatherError::Unsupported(
+                "TAF forecasts, only METAR observations",
+            ));
+        }
+
+        if metar.time.is_none() && token.len() == 7 && token.ends_with('Z') {
+            metar.time = token.get(..6).and_then(parse_time);
+        } else if let Some(wind) = parse_wind(token) {
+            metar.wind = Some(wind);
+        } else if token == "CAVOK" {
+            metar.visibility = Some(10000.0);
+        } else if let Some(miles) = parse_miles(token) {
+            metar.visibility = Some((whole_miles.take().unwrap_or(0.0) + miles) * MILES_TO_METERS);
+        } else if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+            let meters: f64 = token.parse().unwrap_or(0.0);
+            metar.visibility = Some(if meters >= 9999.0 { 10000.0 } else { meters });
+        } else if token.len() == 1 && token.chars().all(|c| c.is_ascii_digit()) {
+            // The whole part of a visibility such as "1 1/2SM".
+            whole_miles = token.parse().ok();
+        } else if let Some(weather) = parse_present_weather(token) {
+            metar.weather.push(weather);
+        } else if let Some(layer) = parse_cloud_layer(token) {
+            metar.clouds.push(layer);
+        } else if let Some((temperature, dewpoint)) = parse_temperatures(token) {
+            metar.temperature = Some(temperature);
+            metar.dewpoint = dewpoint;
+        } else if let Some(pressure) = parse_pressure(token) {
+            metar.pressure = Some(pressure);
+        }
+    }
+
+    Ok(metar)
+}
+
+/// The function that checks whether the token is the validity period of a TAF, such as `1418/1518`.
+fn is_validity_period(token: &str) -> bool {
+    token.len() == 9
+        && token.as_bytes()[4] == b'/'
+        && token
+            .chars()
+            .enumerate()
+            .all(|(i, c)| i == 4 || c.is_ascii_digit())
+}
+
+impl Metar {
+    /// The function that turns the decoded METAR into the app's report model.
+    /// `now` is the current unix time, used to work out the month and year of the observation.
+    pub fn to_report(&self, now: i64) -> Result<WeatherReport, WeatherError> {
+        let temp = self
+            .temperature
+            .ok_or(WeatherError::MissingField("temperature"))?;
+        let humidity = match self.dewpoint {
+            Some(dewpoint) => relative_humidity(temp, dewpoint),
+            None => 0.0,
+        };
+        let wind = self.wind.unwrap_or(MetarWind {
+            direction: None,
+            speed: 0.0,
+            gust: None,
+        });
+
+        Ok(WeatherReport {
+            coord: Coord::default(),
+            weather: self.conditions(),
+            main: MainReadings {
+                temp,
+                feels_like: temp,
+                temp_min: temp,
+                temp_max: temp,
+                pressure: self.pressure.unwrap_or(0.0),
+                humidity,
+                sea_level: self.pressure,
+                grnd_level: None,
+            },
+            visibility: self.visibility.map(|m| m.round() as i64),
+            wind: Wind {
+                speed: wind.speed,
+                deg: wind.direction.unwrap_or(0.0),
+                gust: wind.gust,
+            },
+            clouds: Clouds {
+                all: self.cloud_cover(),
+            },
+            rain: None,
+            snow: None,
+            dt: self
+                .time
+                .map(|(day, hour, minute)| observation_time(day, hour, minute, now))
+                .unwrap_or(now),
+            sys: Sys::default(),
+            timezone: 0,
+            name: self.station.clone(),
+            stale_since: None,
+        })
+    }
+
+    /// The function that returns the highest cloud coverage of all layers as a percentage.
+    pub fn cloud_cover(&self) -> i64 {
+        self.clouds
+            .iter()
+            .map(|layer| cloud_amount_percent(&layer.cover))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The function that returns the OpenWeatherMap conditions matching the present weather groups,
+    /// or the sky condition from the cloud layers when there is no present weather.
+    pub fn conditions(&self) -> Vec<WeatherCondition> {
+        let mut conditions: Vec<WeatherCondition> = self
+            .weather
+            .iter()
+            .filter_map(PresentWeather::condition)
+            .collect();
+
+        if conditions.is_empty() {
+            let highest = self.clouds.iter().max_by_key(|layer| {
+                ["FEW", "SCT", "BKN", "OVC", "VV"]
+                    .iter()
+                    .position(|cover| *cover == layer.cover)
+            });
+            let (id, description) = match self.cloud_cover() {
+                0 => (800, "clear sky".to_string()),
+                25 => (801, "few clouds".to_string()),
+                50 => (802, "scattered clouds".to_string()),
+                75 => (803, "broken clouds".to_string()),
+                _ => (804, "overcast clouds".to_string()),
+            };
+            let description = match highest.and_then(|layer| layer.height) {
+                Some(height) => format!("{} at {} ft", description, height),
+                None => description,
+            };
+            conditions.push(WeatherCondition {
+                id,
+                main: if id == 800 { "Clear" } else { "Clouds" }.to_string(),
+                description,
+                icon: String::new(),
+            });
+        }
+
+        conditions
+    }
+}
+
+impl PresentWeather {
+    fn has(&self, code: &str) -> bool {
+        self.phenomena.iter().any(|p| p == code)
+    }
+
+    /// The function that returns the OpenWeatherMap condition matching the group, such as 201 for `TSRA`.
+    pub fn condition(&self) -> Option<WeatherCondition> {
+        let descriptor = self.descriptor.as_deref();
+        let by_intensity = |light, moderate, heavy| self.intensity.pick(light, moderate, heavy);
+
+        let (id, main) = if self.has("FC") {
+            (781, "Tornado")
+        } else if descriptor == Some("TS") {
+            if self.phenomena.is_empty() {
+                (211, "Thunderstorm")
+            } else {
+                (by_intensity(200, 201, 202), "Thunderstorm")
+            }
+        } else if self.has("SQ") {
+            (771, "Squall")
+        } else if descriptor == Some("FZ") && (self.has("RA") || self.has("DZ")) {
+            (511, "Rain")
+        } else if self.has("PL") || self.has("GR") || self.has("GS") || self.has("IC") {
+            (by_intensity(612, 611, 613), "Snow")
+        } else if self.has("RA") && self.has("SN") {
+            (by_intensity(615, 616, 616), "Snow")
+        } else if self.has("SN") || self.has("SG") {
+            if descriptor == Some("SH") {
+                (by_intensity(620, 621, 622), "Snow")
+            } else {
+                (by_intensity(600, 601, 602), "Snow")
+            }
+        } else if self.has("RA") || self.has("UP") {
+            if descriptor == Some("SH") {
+                (by_intensity(520, 521, 522), "Rain")
+            } else {
+                (by_intensity(500, 501, 502), "Rain")
+            }
+        } else if self.has("DZ") {
+            (by_intensity(300, 301, 302), "Drizzle")
+        } else if self.has("FG") {
+            (741, "Fog")
+        } else if self.has("BR") {
+            (701, "Mist")
+        } else if self.has("FU") {
+            (711, "Smoke")
+        } else if self.has("HZ") {
+            (721, "Haze")
+        } else if self.has("PO") {
+            (731, "Dust")
+        } else if self.has("SA") || self.has("SS") || self.has("DS") {
+            (751, "Sand")
+        } else if self.has("DU") {
+            (761, "Dust")
+        } else if self.has("VA") {
+            (762, "Ash")
+        } else {
+            return None;
+        };
+
+        Some(WeatherCondition {
+            id,
+            main: main.to_string(),
+            description: self.description(),
+            icon: String::new(),
+        })
+    }
+
+    /// The function that describes the group in words, such as "heavy thunderstorm rain" for `+TSRA`.
+    pub fn description(&self) -> String {
+        let mut words: Vec<&str> = Vec::new();
+        match self.intensity {
+            Intensity::Light => words.push("light"),
+            Intensity::Heavy => words.push("heavy"),
+            Intensity::Moderate => {}
+        }
+        if let Some(descriptor) = &self.descriptor {
+            words.push(match descriptor.as_str() {
+                "MI" => "shallow",
+                "PR" => "partial",
+                "BC" => "patches of",
+                "DR" => "low drifting",
+                "BL" => "blowing",
+                "SH" => "showers of",
+                "TS" => "thunderstorm",
+                _ => "freezing",
+            });
+        }
+        for phenomenon in &self.phenomena {
+            words.push(match phenomenon.as_str() {
+                "DZ" => "drizzle",
+                "RA" => "rain",
+                "SN" => "snow",
+                "SG" => "snow grains",
+                "IC" => "ice crystals",
+                "PL" => "ice pellets",
+                "GR" => "hail",
+                "GS" => "small hail",
+                "UP" => "unknown precipitation",
+                "BR" => "mist",
+                "FG" => "fog",
+                "FU" => "smoke",
+                "VA" => "volcanic ash",
+                "DU" => "dust",
+                "SA" => "sand",
+                "HZ" => "haze",
+                "PY" => "spray",
+                "PO" => "dust whirls",
+                "SQ" => "squalls",
+                "FC" => "funnel cloud",
+                "SS" => "sandstorm",
+                _ => "duststorm",
+            });
+        }
+        if self.vicinity {
+            words.push("in the vicinity");
+        }
+        words.join(" ")
+    }
+}
+
+/// The function that converts a sky cover code such as "BKN" into a cloud coverage percentage.
+pub fn cloud_amount_percent(cover: &str) -> i64 {
+    match cover {
+        "FEW" => 25,
+        "SCT" => 50,
+        "BKN" => 75,
+        "OVC" | "VV" => 100,
+        _ => 0,
+    }
+}
+
+/// The function that parses the day, hour and minute of a `DDHHMM` group.
+fn parse_time(digits: &str) -> Option<(u32, u32, u32)> {
+    let day = digits.get(0..2)?.parse().ok()?;
+    let hour = digits.get(2..4)?.parse().ok()?;
+    let minute = digits.get(4..6)?.parse().ok()?;
+    if (1..=31).contains(&day) && hour < 24 && minute < 60 {
+        Some((day, hour, minute))
+    } else {
+        None
+    }
+}
+
+/// The function that parses a wind group such as `20012G20KT`, `VRB03KT` or `27005MPS`.
+fn parse_wind(token: &str) -> Option<MetarWind> {
+    let (body, factor) = if let Some(body) = token.strip_suffix("KT") {
+        (body, KNOTS_TO_MPS)
+    } else if let Some(body) = token.strip_suffix("MPS") {
+        (body, 1.0)
+    } else if let Some(body) = token.strip_suffix("KMH") {
+        (body, 1.0 / 3.6)
+    } else {
+        return None;
+    };
+
+    let direction = match body.get(0..3)? {
+        "VRB" => None,
+        digits => Some(digits.parse::<f64>().ok()?),
+    };
+    let rest = body.get(3..)?;
+    let (speed, gust) = match rest.split_once('G') {
+        Some((speed, gust)) => (speed, Some(gust)),
+        None => (rest, None),
+    };
+
+    Some(MetarWind {
+        direction,
+        speed: speed.parse::<f64>().ok()? * factor,
+        gust: match gust {
+            Some(gust) => Some(gust.parse::<f64>().ok()? * factor),
+            None => None,
+        },
+    })
+}
+
+/// The function that parses a visibility in statute miles such as `10SM`, `1/2SM` or `M1/4SM`.
+fn parse_miles(token: &str) -> Option<f64> {
+    let body = token.strip_suffix("SM")?.trim_start_matches(['M', 'P']);
+    match body.split_once('/') {
+        Some((numerator, denominator)) => {
+            Some(numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?)
+        }
+        None => body.parse().ok(),
+    }
+}
+
+/// The function that parses a present weather group such as `+TSRA`, `VCSH` or `BR`.
+fn parse_present_weather(token: &str) -> Option<PresentWeather> {
+    let (intensity, mut rest) = if let Some(rest) = token.strip_prefix('+') {
+        (Intensity::Heavy, rest)
+    } else if let Some(rest) = token.strip_prefix('-') {
+        (Intensity::Light, rest)
+    } else {
+        (Intensity::Moderate, token)
+    };
+
+    let vicinity = rest.starts_with("VC");
+    if vicinity {
+        rest = &rest[2..];
+    }
+
+    let descriptor = DESCRIPTORS
+        .iter()
+        .find(|descriptor| rest.starts_with(*descriptor))
+        .map(|descriptor| descriptor.to_string());
+    if let Some(descriptor) = &descriptor {
+        rest = &rest[descriptor.len()..];
+    }
+
+    if rest.len() % 2 != 0 {
+        return None;
+    }
+    let mut phenomena = Vec::new();
+    for i in (0..rest.len()).step_by(2) {
+        let code = rest.get(i..i + 2)?;
+        if !PHENOMENA.contains(&code) {
+            return None;
+        }
+        phenomena.push(code.to_string());
+    }
+
+    if phenomena.is_empty() && descriptor.is_none() {
+        return None;
+    }
+
+    Some(PresentWeather {
+        intensity,
+        vicinity,
+        descriptor,
+        phenomena,
+    })
+}
+
+/// The function that parses a cloud layer such as `BKN035`, `OVC010CB` or `VV002`.
+fn parse_cloud_layer(token: &str) -> Option<CloudLayer> {
+    let cover = ["FEW", "SCT", "BKN", "OVC", "VV"]
+        .iter()
+        .find(|cover| token.starts_with(*cover))?;
+    let height = token[cover.len()..]
+        .trim_end_matches("CB")
+        .trim_end_matches("TCU");
+
+    if height.len() != 3 {
+        return None;
+    }
+
+    Some(CloudLayer {
+        cover: cover.to_string(),
+        height: height.parse::<u32>().ok().map(|hundreds| hundreds * 100),
+    })
+}
+
+/// The function that parses a temperature and dewpoint group such as `22/18` or `M05/M12`.
+fn parse_temperatures(token: &str) -> Option<(f64, Option<f64>)> {
+    let (temperature, dewpoint) = token.split_once('/')?;
+    let degrees = |text: &str| -> Option<f64> {
+        let (sign, digits) = match text.strip_prefix('M') {
+            Some(digits) => (-1.0, digits),
+            None => (1.0, text),
+        };
+        if digits.len() != 2 {
+            return None;
+        }
+        Some(sign * digits.parse::<f64>().ok()?)
+    };
+
+    Some((degrees(temperature)?, degrees(dewpoint)))
+}
+
+/// The function that parses an altimeter group, `A2992` in inches of mercury or `Q1013` in hectopascals.
+fn parse_pressure(token: &str) -> Option<f64> {
+    if token.len() != 5 {
+        return None;
+    }
+    let value: f64 = token.get(1..)?.parse().ok()?;
+    match token.get(..1)? {
+        "A" => Some(value / 100.0 * 33.8639),
+        "Q" => Some(value),
+        _ => None,
+    }
+}
+
+/// The function that estimates the relative humidity from the temperature and dewpoint.
+fn relative_humidity(temperature: f64, dewpoint: f64) -> f64 {
+    let magnus = |t: f64| (17.625 * t / (243.04 + t)).exp();
+    (100.0 * magnus(dewpoint) / magnus(temperature)).clamp(0.0, 100.0)
+}
+
+/// The function that returns the unix time of the most recent day of the month, hour and minute before `now`.
+fn observation_time(day: u32, hour: u32, minute: u32, now: i64) -> i64 {
+    let today = now.div_euclid(86400);
+    for days_ago in 0..62 {
+        let date = today - days_ago;
+        if day_of_month(date) == day {
+            let time = date * 86400 + hour as i64 * 3600 + minute as i64 * 60;
+            // Reports are at most a day in the future because of clock drift, never more.
+            if time <= now + 86400 {
+                return time;
+            }
+        }
+    }
+    now
+}
+
+/// The function that returns the day of the month for a number of days since the unix epoch.
+fn day_of_month(days: i64) -> u32 {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    (doy - (153 * mp + 2) / 5 + 1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metar() {
+        let metar =
+            parse("METAR KBNA 142153Z 20012G20KT 8SM -TSRA BR BKN035 OVC080CB 22/18 A2992 RMK AO2")
+                .unwrap();
+
+        assert_eq!(metar.station, "KBNA");
+        assert_eq!(metar.time, Some((14, 21, 53)));
+        let wind = metar.wind.unwrap();
+        assert_eq!(wind.direction, Some(200.0));
+        assert!((wind.speed - 6.173).abs() < 1e-3);
+        assert!((wind.gust.unwrap() - 10.289).abs() < 1e-3);
+        assert!((metar.visibility.unwrap() - 12874.752).abs() < 1e-3);
+        assert_eq!(metar.weather.len(), 2);
+        assert_eq!(metar.weather[0].intensity, Intensity::Light);
+        assert_eq!(metar.weather[0].descriptor.as_deref(), Some("TS"));
+        assert_eq!(metar.weather[0].phenomena, vec!["RA"]);
+        assert_eq!(metar.weather[1].phenomena, vec!["BR"]);
+        assert_eq!(metar.clouds.len(), 2);
+        assert_eq!(metar.clouds[0].cover, "BKN");
+        assert_eq!(metar.clouds[0].height, Some(3500));
+        assert_eq!(metar.temperature, Some(22.0));
+        assert_eq!(metar.dewpoint, Some(18.0));
+        assert!((metar.pressure.unwrap() - 1013.2).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_parse_icao_style_metar() {
+        let metar =
+            parse("EGLL 141950Z AUTO VRB03KT 0300 R27L/0550 FG VV002 M01/M01 Q1021=").unwrap();
+
+        assert_eq!(metar.wind.unwrap().direction, None);
+        assert_eq!(metar.visibility, Some(300.0));
+        assert_eq!(metar.weather[0].phenomena, vec!["FG"]);
+        assert_eq!(metar.clouds[0].cover, "VV");
+        assert_eq!(metar.temperature, Some(-1.0));
+        assert_eq!(metar.pressure, Some(1021.0));
+        assert_eq!(metar.cloud_cover(), 100);
+    }
+
+    #[test]
+    fn test_fractional_visibility() {
+        let metar = parse("KORD 141951Z 36015KT 1 1/2SM +SN OVC008 M02/M04 A2980").unwrap();
+        assert!((metar.visibility.unwrap() - 1.5 * MILES_TO_METERS).abs() < 1e-6);
+        assert_eq!(metar.weather[0].intensity, Intensity::Heavy);
+    }
+
+    #[test]
+    fn test_present_weather_conditions() {
+        let id = |group| {
+            parse_present_weather(group)
+                .unwrap()
+                .condition()
+                .unwrap()
+                .id
+        };
+
+        assert_eq!(id("+TSRA"), 202);
+        assert_eq!(id("TSRA"), 201);
+        assert_eq!(id("-TSRA"), 200);
+        assert_eq!(id("TS"), 211);
+        assert_eq!(id("-RA"), 500);
+        assert_eq!(id("+SHRA"), 522);
+        assert_eq!(id("-DZ"), 300);
+        assert_eq!(id("FZRA"), 511);
+        assert_eq!(id("SN"), 601);
+        assert_eq!(id("-SHSN"), 620);
+        assert_eq!(id("-RASN"), 615);
+        assert_eq!(id("PL"), 611);
+        assert_eq!(id("FG"), 741);
+        assert_eq!(id("BR"), 701);
+        assert_eq!(id("HZ"), 721);
+        assert_eq!(id("FU"), 711);
+        assert_eq!(id("SQ"), 771);
+        assert_eq!(id("+FC"), 781);
+        assert_eq!(
+            parse_present_weather("+TSRA").unwrap().description(),
+            "heavy thunderstorm rain"
+        );
+        assert!(parse_present_weather("BKN035").is_none());
+        assert!(parse_present_weather("RMK").is_none());
+    }
+
+    #[test]
+    fn test_to_report() {
+        let now = 1700000000; // 2023-11-14T22:13:20Z
+        let report = parse("KBNA 142153Z 20012G20KT 8SM -TSRA BR BKN035 22/18 A2992")
+            .unwrap()
+            .to_report(now)
+            .unwrap();
+
+        assert_eq!(report.name, "KBNA");
+        assert_eq!(report.weather_id(), 200);
+        assert_eq!(report.weather[1].id, 701);
+        assert_eq!(report.clouds.all, 75);
+        assert_eq!(report.dt, 1699998780);
+        assert_eq!(report.wind.deg, 200.0);
+        assert!(report.main.humidity > 75.0 && report.main.humidity < 80.0);
+    }
+
+    #[test]
+    fn test_cloud_layers_without_weather() {
+        let id = |text| parse(text).unwrap().to_report(0).unwrap().weather_id();
+
+        assert_eq!(id("KJFK 141951Z 31008KT 10SM CLR 12/01 A3010"), 800);
+        assert_eq!(id("KJFK 141951Z 31008KT 10SM FEW250 12/01 A3010"), 801);
+        assert_eq!(id("KJFK 141951Z 31008KT 10SM SCT050 12/01 A3010"), 802);
+        assert_eq!(
+            id("KJFK 141951Z 31008KT 10SM FEW020 BKN050 12/01 A3010"),
+            803
+        );
+        assert_eq!(id("KJFK 141951Z 31008KT 10SM OVC015 12/01 A3010"), 804);
+    }
+
+    #[test]
+    fn test_taf_is_not_supported() {
+        assert!(matches!(
+            parse("TAF KBNA 141720Z 1418/1518 20012KT P6SM VCSH BKN040 FM150200 27005KT P6SM SKC"),
+            Err(WeatherError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse("KBNA 141720Z 1418/1518 20012KT P6SM VCSH BKN040"),
+            Err(WeatherError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_metar() {
+        assert!(matches!(parse(""), Err(WeatherError::InvalidMetar(_))));
+        assert!(matches!(
+            parse("London"),
+            Err(WeatherError::InvalidMetar(_))
+        ));
+    }
+}
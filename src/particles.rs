@@ -0,0 +1,360 @@
+//! The particle simulation for precipitation and haze.
+//!
+//! Particles are created once for a scene and then moved every update, so rain falls and snow drifts
+//! instead of being scattered randomly on every frame. The number of particles stays the same for as
+//! long as the scene is shown: a particle leaving the window is respawned at the top.
+
+use crate::scene::Layers;
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+
+/// The acceleration of falling particles in pixels per second squared.
+const GRAVITY: f32 = 1500.0;
+
+/// The height below the top of the window where precipitation starts, so it falls from the clouds.
+const CLOUD_BASE: f32 = 200.0;
+
+/// The kind of a particle, which decides how it moves and how it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleKind {
+    /// Rain drops fall fast and are drawn as streaks.
+    Rain,
+    /// Snow flakes fall slowly and drift from side to side.
+    Snow,
+    /// Sleet pellets fall fast and bounce once off the ground.
+    Sleet,
+    /// Mist, smoke and dust particles float slowly sideways.
+    Haze,
+}
+
+impl ParticleKind {
+    /// The function that returns the fastest the particle falls, in pixels per second.
+    pub fn terminal_speed(self) -> f32 {
+        match self {
+            ParticleKind::Rain => 900.0,
+            ParticleKind::Snow => 80.0,
+            ParticleKind::Sleet => 500.0,
+            ParticleKind::Haze => 0.0,
+        }
+    }
+}
+
+/// A single particle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    pub kind: ParticleKind,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub color: Srgb<u8>,
+    /// The offset of the particle's sway, so snow flakes do not drift in step.
+    pub phase: f32,
+    /// The sideways speed of a haze particle without wind.
+    pub drift: f32,
+    /// Whether a sleet pellet already bounced off the ground.
+    pub bounced: bool,
+}
+
+/// The particles of a scene.
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+    /// How fast the wind moves the particles sideways in pixels per second.
+    pub wind: f32,
+    /// The seconds the simulation has run, used for the sway of snow flakes.
+    time: f32,
+    /// The random numbers for spawning particles, so the same seed always gives the same simulation.
+    rng: StdRng,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            wind: 0.0,
+            time: 0.0,
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// The function that creates the particles for the rain, sleet, snow and haze layers of a scene.
+    /// The particles are spread over the window so the scene does not start empty, and are carried sideways
+    /// by `wind` pixels per second. The same seed always places and moves the particles the same way.
+    pub fn from_layers(layers: &Layers, bounds: Rect, wind: f32, seed: u64) -> ParticleSystem {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut particles = Vec::new();
+        let mut spawn = |kind: ParticleKind, color: Srgb<u8>, count: i32| {
+            for _ in 0..count.max(0) {
+                let mut particle = Particle::spawn(kind, color, bounds, &mut rng);
+                particle.position.y = if kind == ParticleKind::Haze {
+                    rng.gen_range(bounds.bottom()..bounds.top())
+                } else {
+                    rng.gen_range(bounds.bottom()..cloud_base(bounds))
+                };
+                particles.push(particle);
+            }
+        };
+
+        for (color, count) in &layers.haze {
+            spawn(ParticleKind::Haze, *color, *count);
+        }
+        for (color, count) in &layers.rain {
+            spawn(ParticleKind::Rain, *color, *count);
+        }
+        spawn(ParticleKind::Sleet, WHITE, layers.sleet);
+        spawn(ParticleKind::Snow, WHITE, layers.snow);
+
+        ParticleSystem {
+            particles,
+            wind,
+            time: 0.0,
+            rng,
+        }
+    }
+
+    /// The function that moves every particle forward by `dt` seconds.
+    /// Particles blown out of a side of the window come back in on the other side, and falling particles
+    /// reaching the bottom are respawned at the top.
+    pub fn update(&mut self, dt: f32, bounds: Rect) {
+        self.time += dt;
+
+        for particle in &mut self.particles {
+            particle.advance(dt, self.time, self.wind);
+
+            if particle.position.x > bounds.right() {
+                particle.position.x = bounds.left();
+            } else if particle.position.x < bounds.left() {
+                particle.position.x = bounds.right();
+            }
+
+            if particle.kind != ParticleKind::Haze && particle.position.y < bounds.bottom() {
+                if particle.kind == ParticleKind::Sleet && !particle.bounced {
+                    particle.position.y = bounds.bottom();
+                    particle.velocity.y = -particle.velocity.y * 0.3;
+                    particle.bounced = true;
+                } else {
+                    // The respawned particle keeps moving with the wind until its next update
+                    let wind_speed = particle.velocity.x;
+                    *particle =
+                        Particle::spawn(particle.kind, particle.color, bounds, &mut self.rng);
+                    particle.velocity.x = wind_speed;
+                }
+            }
+        }
+    }
+
+    /// The function that draws every particle.
+    pub fn draw(&self, draw: &Draw) {
+        for particle in &self.particles {
+            match particle.kind {
+                ParticleKind::Rain => {
+                    let tail = particle.position - particle.velocity * 0.03;
+                    draw.line()
+                        .start(tail)
+                        .end(particle.position)
+                        .weight(3.0)
+                        .color(particle.color);
+                }
+                ParticleKind::Snow | ParticleKind::Sleet => {
+                    let radius = if particle.kind == ParticleKind::Snow {
+                        10.0
+                    } else {
+                        6.0
+                    };
+                    draw.ellipse()
+                        .xy(particle.position)
+                        .radius(radius)
+                        .color(particle.color);
+                }
+                ParticleKind::Haze => {
+                    draw.ellipse()
+                        .xy(particle.position)
+                        .radius(1.0)
+                        .color(particle.color);
+                }
+            }
+        }
+    }
+}
+
+/// The function that returns the height precipitation falls from, `CLOUD_BASE` below the top of the window
+/// or half way down a window too small for it.
+fn cloud_base(bounds: Rect) -> f32 {
+    bounds.top() - CLOUD_BASE.min(bounds.h() / 2.0)
+}
+
+impl Particle {
+    /// The function that creates a particle at a random place along the top of where it falls from.
+    fn spawn(kind: ParticleKind, color: Srgb<u8>, bounds: Rect, rng: &mut StdRng) -> Particle {
+        let x = rng.gen_range(bounds.left()..bounds.right());
+        let y = match kind {
+            ParticleKind::Haze => rng.gen_range(bounds.bottom()..bounds.top()),
+            _ => cloud_base(bounds),
+        };
+
+        Particle {
+            kind,
+            position: pt2(x, y),
+            velocity: vec2(0.0, -rng.gen_range(0.2..0.5) * kind.terminal_speed()),
+            color,
+            phase: rng.gen_range(0.0..TAU),
+            drift: rng.gen_range(5.0..20.0),
+            bounced: false,
+        }
+    }
+
+    /// The function that applies gravity up to the terminal speed and the wind, then moves the particle.
+    /// Falling particles are carried at the wind speed, light haze particles only drift with part of it.
+    fn advance(&mut self, dt: f32, time: f32, wind: f32) {
+        match self.kind {
+            ParticleKind::Haze => self.velocity.x = self.drift + wind * 0.5,
+            ParticleKind::Snow => self.velocity.x = wind + (time * 1.5 + self.phase).sin() * 30.0,
+            ParticleKind::Rain | ParticleKind::Sleet => self.velocity.x = wind,
+        }
+        if self.kind != ParticleKind::Haze {
+            self.velocity.y = (self.velocity.y - GRAVITY * dt).max(-self.kind.terminal_speed());
+        }
+        self.position += self.velocity * dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::condition::{Condition, Intensity};
+
+    fn bounds() -> Rect {
+        Rect::from_w_h(1024.0, 512.0)
+    }
+
+    #[test]
+    fn test_count_follows_intensity() {
+        let light = Layers::from_conditions(&[Condition::Rain(Intensity::Light)]);
+        let heavy = Layers::from_conditions(&[Condition::Rain(Intensity::Heavy)]);
+
+        assert_eq!(
+            ParticleSystem::from_layers(&light, bounds(), 0.0, 1)
+                .particles
+                .len(),
+            25
+        );
+        assert_eq!(
+            ParticleSystem::from_layers(&heavy, bounds(), 0.0, 1)
+                .particles
+                .len(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_count_is_stable() {
+        let layers = Layers::from_conditions(&[
+            Condition::Snow(Intensity::Light),
+            Condition::Sleet(Intensity::Light),
+            Condition::Mist,
+        ]);
+        let mut system = ParticleSystem::from_layers(&layers, bounds(), 100.0, 1);
+        let count = system.particles.len();
+
+        for _ in 0..600 {
+            system.update(1.0 / 60.0, bounds());
+        }
+
+        assert_eq!(system.particles.len(), count);
+        for particle in &system.particles {
+            assert!(bounds().pad(-1.0).contains(particle.position));
+        }
+    }
+
+    #[test]
+    fn test_small_window() {
+        let layers = Layers::from_conditions(&[
+            Condition::Rain(Intensity::Heavy),
+            Condition::Snow(Intensity::Light),
+            Condition::Fog,
+        ]);
+        let small = Rect::from_w_h(160.0, 120.0);
+        let mut system = ParticleSystem::from_layers(&layers, small, 0.0, 1);
+
+        for particle in &system.particles {
+            assert!(small.contains(particle.position));
+            if particle.kind != ParticleKind::Haze {
+                assert!(particle.position.y <= 0.0);
+            }
+        }
+        for _ in 0..120 {
+            system.update(1.0 / 60.0, small);
+        }
+        for particle in &system.particles {
+            assert!(small.pad(-1.0).contains(particle.position));
+        }
+    }
+
+    #[test]
+    fn test_terminal_speed() {
+        let layers = Layers::from_conditions(&[Condition::Rain(Intensity::Moderate)]);
+        let mut system = ParticleSystem::from_layers(&layers, bounds(), 0.0, 1);
+
+        for _ in 0..10 {
+            system.update(1.0 / 60.0, bounds());
+        }
+
+        for particle in &system.particles {
+            assert!(particle.velocity.y >= -ParticleKind::Rain.terminal_speed());
+        }
+    }
+
+    #[test]
+    fn test_rain_slants_with_wind() {
+        let layers = Layers::from_conditions(&[Condition::Rain(Intensity::Light)]);
+        let mut system = ParticleSystem::from_layers(&layers, bounds(), -80.0, 1);
+
+        system.update(1.0 / 60.0, bounds());
+
+        for particle in &system.particles {
+            assert_eq!(particle.velocity.x, -80.0);
+            assert!(particle.velocity.y < 0.0);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_simulation() {
+        let layers = Layers::from_conditions(&[Condition::Snow(Intensity::Heavy)]);
+        let mut first = ParticleSystem::from_layers(&layers, bounds(), 30.0, 42);
+        let mut second = ParticleSystem::from_layers(&layers, bounds(), 30.0, 42);
+        let other = ParticleSystem::from_layers(&layers, bounds(), 30.0, 43);
+
+        assert_eq!(first.particles, second.particles);
+        assert_ne!(first.particles, other.particles);
+
+        // Long enough for flakes to reach the ground and respawn
+        for _ in 0..600 {
+            first.update(1.0 / 60.0, bounds());
+            second.update(1.0 / 60.0, bounds());
+        }
+        assert_eq!(first.particles, second.particles);
+    }
+
+    #[test]
+    fn test_sleet_bounces() {
+        let mut system = ParticleSystem {
+            particles: vec![Particle {
+                kind: ParticleKind::Sleet,
+                position: pt2(0.0, bounds().bottom() + 1.0),
+                velocity: vec2(0.0, -500.0),
+                color: WHITE,
+                phase: 0.0,
+                drift: 0.0,
+                bounced: false,
+            }],
+            ..ParticleSystem::default()
+        };
+
+        system.update(1.0 / 60.0, bounds());
+
+        assert!(system.particles[0].bounced);
+        assert!(system.particles[0].velocity.y > 0.0);
+    }
+}
@@ -0,0 +1,204 @@
+use super::{get_text, Location, Place, WeatherProvider};
+use crate::error::WeatherError;
+use crate::metar;
+use crate::report::{Forecast, WeatherReport};
+use reqwest::blocking::Client;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the raw METAR reports are read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetarSource {
+    /// The report is typed or piped in where the app asks for a city.
+    Stdin,
+    /// A text file with one report per line, looked up by station code.
+    File(PathBuf),
+    /// A server with the aviationweather.gov `/api/data/metar` endpoint, such as a local stand-in.
+    Http(String),
+}
+
+impl MetarSource {
+    /// The function that reads the source from a setting: "stdin", an http(s) URL, or a file path.
+    pub fn from_setting(setting: &str) -> MetarSource {
+        let setting = setting.trim();
+        if setting.is_empty() || setting == "stdin" || setting == "-" {
+            MetarSource::Stdin
+        } else if setting.starts_with("http://") || setting.starts_with("https://") {
+            MetarSource::Http(setting.trim_end_matches('/').to_string())
+        } else {
+            MetarSource::File(PathBuf::from(setting))
+        }
+    }
+}
+
+/// The METAR backend for aviation users.
+/// Instead of a weather API, it decodes raw METAR reports into the app's report model.
+pub struct MetarProvider {
+    client: Client,
+    source: MetarSource,
+}
+
+impl MetarProvider {
+    /// The function that creates the provider reading from the given source.
+    pub fn new(source: MetarSource) -> MetarProvider {
+        MetarProvider {
+            client: Client::new(),
+            source,
+        }
+    }
+
+    /// The function that creates the provider using the `METAR_SOURCE` from the environment,
+    /// reading from stdin when it is not set.
+    pub fn from_env() -> MetarProvider {
+        let setting = env::var("METAR_SOURCE").unwrap_or_default();
+        MetarProvider::new(MetarSource::from_setting(&setting))
+    }
+
+    /// The function that returns the raw report for the location from the source.
+    fn raw_report(&self, location: &Location) -> Result<String, WeatherError> {
+        let query = match location {
+            Location::City(query) => query.trim().to_uppercase(),
+            Location::Coords { .. } => String::new(),
+        };
+
+        match &self.source {
+            MetarSource::Stdin => {
+                if query.is_empty() {
+                    Err(WeatherError::NotFound)
+                } else {
+                    Ok(query)
+                }
+            }
+            MetarSource::File(path) => {
+                let contents = fs::read_to_string(path)?;
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .find(|line| {
+                        query.is_empty()
+                            || metar::parse(line)
+                                .map(|metar| metar.station == query)
+                                .unwrap_or(false)
+                    })
+                    .map(str::to_string)
+                    .ok_or(WeatherError::NotFound)
+            }
+            MetarSource::Http(base_url) => {
+                let request = self
+                    .client
+                    .get(format!("{}/api/data/metar", base_url))
+                    .query(&[("ids", query.as_str()), ("format", "raw")]);
+                let body = get_text(request)?;
+                body.lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .ok_or(WeatherError::NotFound)
+            }
+        }
+    }
+}
+
+impl WeatherProvider for MetarProvider {
+    fn name(&self) -> &'static str {
+        "METAR"
+    }
+
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let raw = self.raw_report(location)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        metar::parse(&raw)?.to_report(now)
+    }
+
+    fn forecast(&self, _location: &Location) -> Result<Forecast, WeatherError> {
+        Err(WeatherError::Unsupported("forecasts"))
+    }
+
+    fn geocode(&self, _query: &str) -> Result<Vec<Place>, WeatherError> {
+        Err(WeatherError::Unsupported("looking up places"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn test_source_from_setting() {
+        assert_eq!(MetarSource::from_setting(""), MetarSource::Stdin);
+        assert_eq!(MetarSource::from_setting("stdin"), MetarSource::Stdin);
+        assert_eq!(
+            MetarSource::from_setting("http://localhost:8080/"),
+            MetarSource::Http("http://localhost:8080".to_string())
+        );
+        assert_eq!(
+            MetarSource::from_setting("metars.txt"),
+            MetarSource::File(PathBuf::from("metars.txt"))
+        );
+    }
+
+    #[test]
+    fn test_stdin_source() {
+        let provider = MetarProvider::new(MetarSource::Stdin);
+        let report = provider
+            .current(&Location::City(
+                "egll 141950Z 24012KT 9999 -RA BKN012 11/09 Q1004".to_string(),
+            ))
+            .unwrap();
+
+        assert_eq!(report.name, "EGLL");
+        assert_eq!(report.weather_id(), 500);
+    }
+
+    #[test]
+    fn test_file_source() {
+        let path = env::temp_dir().join(format!("metars-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# recorded reports\nKJFK 141951Z 31008KT 10SM FEW250 12/01 A3010\nKORD 141951Z 36015KT 1/2SM +SN OVC008 M02/M04 A2980\n",
+        )
+        .unwrap();
+
+        let provider = MetarProvider::new(MetarSource::File(path.clone()));
+        let report = provider
+            .current(&Location::City("kord".to_string()))
+            .unwrap();
+        let missing = provider.current(&Location::City("KBNA".to_string()));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(report.name, "KORD");
+        assert_eq!(report.weather_id(), 602);
+        assert_eq!(report.clouds.all, 100);
+        assert!(matches!(missing, Err(WeatherError::NotFound)));
+    }
+
+    #[test]
+    fn test_http_source() {
+        let _m = mock("GET", "/api/data/metar")
+            .match_query(Matcher::UrlEncoded("ids".into(), "KBNA".into()))
+            .with_status(200)
+            .with_header("content-type", "text/plain")
+            .with_body("KBNA 142153Z 20012G20KT 8SM +TSRA BKN035 22/18 A2992\n")
+            .create();
+
+        let provider = MetarProvider::new(MetarSource::Http(mockito::server_url()));
+        let report = provider
+            .current(&Location::City("KBNA".to_string()))
+            .unwrap();
+
+        assert_eq!(report.weather_id(), 202);
+        assert_eq!(report.clouds.all, 75);
+        assert!(matches!(
+            provider.forecast(&Location::City("KBNA".to_string())),
+            Err(WeatherError::Unsupported(_))
+        ));
+    }
+}
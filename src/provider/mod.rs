@@ -0,0 +1,157 @@
+pub mod metar;
+pub mod nws;
+pub mod open_meteo;
+pub mod openweathermap;
+
+use crate::error::WeatherError;
+use crate::report::{Forecast, WeatherReport};
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The hourly forecasts of Open-Meteo and the NWS are thinned out to every third hour to match the
+/// OpenWeatherMap forecast slots.
+pub(crate) const FORECAST_STEP_HOURS: usize = 3;
+
+/// The number of requests sent to weather APIs since the app started.
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// The place the weather should be fetched for, either by name or by coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    City(String),
+    Coords { lat: f64, lon: f64 },
+}
+
+/// A place returned by a geocoding lookup.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Place {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+impl Place {
+    /// The function that returns the name of the place with its state and country, such as "Paris, Texas, US".
+    pub fn label(&self) -> String {
+        let mut parts = vec![self.name.as_str()];
+        if let Some(state) = self.state.as_deref().filter(|s| !s.is_empty()) {
+            parts.push(state);
+        }
+        if !self.country.is_empty() {
+            parts.push(&self.country);
+        }
+        parts.join(", ")
+    }
+
+    /// The function that returns the coordinates of the place as a location to fetch the weather for.
+    pub fn location(&self) -> Location {
+        Location::Coords {
+            lat: self.lat,
+            lon: self.lon,
+        }
+    }
+}
+
+/// A source of weather data.
+/// Each backend turns its own API into the app's `WeatherReport` and `Forecast` types so the
+/// renderers do not need to know where the data came from.
+/// The window fetches the weather on a worker thread, so providers must be `Send`.
+pub trait WeatherProvider: Send {
+    /// The function that returns the name of the provider, used in messages to the user.
+    fn name(&self) -> &'static str;
+
+    /// The function that fetches the current weather for the location.
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError>;
+
+    /// The function that fetches the current weather for the location again, even when a recent report is
+    /// kept. Providers that keep no reports fetch it as usual.
+    fn refresh(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        self.current(location)
+    }
+
+    /// The function that fetches the upcoming forecast for the location.
+    fn forecast(&self, location: &Location) -> Result<Forecast, WeatherError>;
+
+    /// The function that fetches the upcoming forecast for the location again, even when a recent forecast
+    /// is kept. Providers that keep no forecasts fetch it as usual.
+    fn refresh_forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        self.forecast(location)
+    }
+
+    /// The function that looks up the places matching the query.
+    fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError>;
+}
+
+/// The names of the providers, as the user can pick them with `--provider`.
+pub const NAMES: [&str; 4] = ["openweathermap", "open-meteo", "nws", "metar"];
+
+/// The function that returns the name of the provider for a name or alias the user typed, such as
+/// "open-meteo" for "OpenMeteo" or "nws" for "weather.gov".
+/// The function returns `None` if there is no provider with that name.
+pub fn canonical_name(name: &str) -> Option<&'static str> {
+    match name
+        .to_lowercase()
+        .replace(['-', '_', ' ', '.'], "")
+        .as_str()
+    {
+        "openweathermap" | "owm" => Some("openweathermap"),
+        "openmeteo" => Some("open-meteo"),
+        "nws" | "weathergov" | "nationalweatherservice" => Some("nws"),
+        "metar" => Some("metar"),
+        _ => None,
+    }
+}
+
+/// The function that creates the provider with the given name, such as "openweathermap", "open-meteo", "nws" or "metar".
+/// The function returns `None` if there is no provider with that name.
+pub fn from_name(name: &str) -> Option<Box<dyn WeatherProvider>> {
+    match canonical_name(name)? {
+        "openweathermap" => Some(Box::new(openweathermap::OpenWeatherMap::from_env())),
+        "open-meteo" => Some(Box::new(open_meteo::OpenMeteo::default())),
+        "nws" => Some(Box::new(nws::Nws::default())),
+        "metar" => Some(Box::new(metar::MetarProvider::from_env())),
+        _ => None,
+    }
+}
+
+/// The function that returns the number of requests sent to weather APIs since the app started, to
+/// check how much of the API quota the app uses.
+pub fn request_count() -> u64 {
+    REQUESTS.load(Ordering::Relaxed)
+}
+
+/// The function that sends the request and counts it.
+fn send(request: RequestBuilder) -> Result<Response, WeatherError> {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    Ok(request.send()?)
+}
+
+/// The function that sends the request and decodes the JSON body of a successful response.
+/// Unsuccessful statuses are turned into the matching `WeatherError`.
+pub(crate) fn get_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, WeatherError> {
+    let response = send(request)?;
+
+    if !response.status().is_success() {
+        return Err(WeatherError::from_status(response.status()));
+    }
+
+    let body = response.text()?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The function that sends the request and returns the plain text body of a successful response.
+pub(crate) fn get_text(request: RequestBuilder) -> Result<String, WeatherError> {
+    let response = send(request)?;
+
+    if !response.status().is_success() {
+        return Err(WeatherError::from_status(response.status()));
+    }
+
+    Ok(response.text()?)
+}
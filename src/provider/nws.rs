@@ -0,0 +1,660 @@
+use super::open_meteo::{OpenMeteo, DEFAULT_FORECAST_URL, DEFAULT_GEOCODING_URL};
+use super::{get_json, Location, Place, WeatherProvider, FORECAST_STEP_HOURS};
+use crate::error::WeatherError;
+use crate::metar::cloud_amount_percent;
+use crate::report::{
+    Clouds, Coord, Forecast, ForecastCity, ForecastSlot, MainReadings, Sys, WeatherCondition,
+    WeatherReport, Wind,
+};
+use crate::wind;
+use reqwest::blocking::Client;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use serde::Deserialize;
+
+/// The base URL of the US National Weather Service API.
+pub const DEFAULT_BASE_URL: &str = "https://api.weather.gov";
+
+/// The NWS asks every client to identify itself with a User-Agent.
+const NWS_USER_AGENT: &str = "weather_visualizer (github.com/haleykahn14/rust-weather-visualizer)";
+
+/// The US National Weather Service (api.weather.gov) backend.
+/// NWS only covers the United States and has no geocoder, so city names are looked up with Open-Meteo's
+/// geocoding API and limited to places in the US.
+pub struct Nws {
+    client: Client,
+    base_url: String,
+    geocoder: OpenMeteo,
+}
+
+#[derive(Deserialize)]
+struct Feature<T> {
+    properties: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Point {
+    grid_id: String,
+    grid_x: i64,
+    grid_y: i64,
+    relative_location: Option<Feature<RelativeLocation>>,
+}
+
+#[derive(Deserialize)]
+struct RelativeLocation {
+    city: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct Periods {
+    periods: Vec<Period>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Period {
+    start_time: String,
+    temperature: f64,
+    temperature_unit: String,
+    #[serde(default)]
+    wind_speed: Option<String>,
+    #[serde(default)]
+    wind_direction: Option<String>,
+    #[serde(default)]
+    short_forecast: String,
+    #[serde(default)]
+    probability_of_precipitation: Option<Measurement>,
+    #[serde(default)]
+    relative_humidity: Option<Measurement>,
+}
+
+#[derive(Deserialize)]
+struct Stations {
+    features: Vec<Feature<Station>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Station {
+    station_identifier: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Observation {
+    timestamp: String,
+    #[serde(default)]
+    text_description: String,
+    #[serde(default)]
+    temperature: Measurement,
+    #[serde(default)]
+    wind_direction: Measurement,
+    #[serde(default)]
+    wind_speed: Measurement,
+    #[serde(default)]
+    wind_gust: Measurement,
+    #[serde(default)]
+    barometric_pressure: Measurement,
+    #[serde(default)]
+    sea_level_pressure: Measurement,
+    #[serde(default)]
+    visibility: Measurement,
+    #[serde(default)]
+    relative_humidity: Measurement,
+    #[serde(default)]
+    heat_index: Measurement,
+    #[serde(default)]
+    wind_chill: Measurement,
+    #[serde(default)]
+    cloud_layers: Vec<CloudLayer>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Measurement {
+    value: Option<f64>,
+    #[serde(default)]
+    unit_code: String,
+}
+
+#[derive(Deserialize)]
+struct CloudLayer {
+    amount: String,
+}
+
+impl Measurement {
+    /// The function that returns the value converted to meters per second if it is a speed in km/h.
+    fn speed(&self) -> Option<f64> {
+        match self.unit_code.as_str() {
+            "wmoUnit:km_h-1" => self.value.map(|v| v / 3.6),
+            _ => self.value,
+        }
+    }
+}
+
+impl Default for Nws {
+    fn default() -> Nws {
+        Nws::new(
+            DEFAULT_BASE_URL,
+            OpenMeteo::new(DEFAULT_FORECAST_URL, DEFAULT_GEOCODING_URL),
+        )
+    }
+}
+
+impl Nws {
+    /// The function that creates the provider with the given base URL and geocoder for city names.
+    pub fn new(base_url: &str, geocoder: OpenMeteo) -> Nws {
+        Nws {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            geocoder,
+        }
+    }
+
+    /// The function that fetches the JSON at the given path of the API.
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, WeatherError> {
+        let request = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .header(USER_AGENT, NWS_USER_AGENT)
+            .header(ACCEPT, "application/geo+json");
+
+        get_json(request)
+    }
+
+    /// The function that turns the location into coordinates, geocoding city names with the first US match.
+    fn resolve(&self, location: &Location) -> Result<(f64, f64), WeatherError> {
+        match location {
+            Location::Coords { lat, lon } => Ok((*lat, *lon)),
+            Location::City(city) => {
+                let place = self
+                    .geocode(city)?
+                    .into_iter()
+                    .next()
+                    .ok_or(WeatherError::NotFound)?;
+                Ok((place.lat, place.lon))
+            }
+        }
+    }
+
+    /// The function that looks up the forecast grid point for the coordinates.
+    fn point(&self, lat: f64, lon: f64) -> Result<Point, WeatherError> {
+        let point: Feature<Point> = self.get(&format!("/points/{:.4},{:.4}", lat, lon))?;
+        Ok(point.properties)
+    }
+
+    /// The function that returns the forecast periods for the grid point, either the 12 hour or hourly ones.
+    fn periods(&self, point: &Point, hourly: bool) -> Result<Vec<Period>, WeatherError> {
+        let suffix = if hourly { "/hourly" } else { "" };
+        let forecast: Feature<Periods> = self.get(&format!(
+            "/gridpoints/{}/{},{}/forecast{}",
+            point.grid_id, point.grid_x, point.grid_y, suffix
+        ))?;
+        Ok(forecast.properties.periods)
+    }
+
+    /// The function that returns the latest observation from the station closest to the grid point.
+    fn latest_observation(&self, point: &Point) -> Result<Observation, WeatherError> {
+        let stations: Stations = self.get(&format!(
+            "/gridpoints/{}/{},{}/stations",
+            point.grid_id, point.grid_x, point.grid_y
+        ))?;
+        let station = stations
+            .features
+            .first()
+            .ok_or(WeatherError::MissingField("features"))?;
+        let observation: Feature<Observation> = self.get(&format!(
+            "/stations/{}/observations/latest",
+            station.properties.station_identifier
+        ))?;
+        Ok(observation.properties)
+    }
+}
+
+impl WeatherProvider for Nws {
+    fn name(&self) -> &'static str {
+        "National Weather Service"
+    }
+
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let (lat, lon) = self.resolve(location)?;
+        let point = self.point(lat, lon)?;
+        let periods = self.periods(&point, false)?;
+        let period = periods
+            .first()
+            .ok_or(WeatherError::MissingField("periods"))?;
+        let observation = self.latest_observation(&point)?;
+
+        // Stations often report no temperature for a while, so the forecast fills in the gaps.
+        let temp = observation
+            .temperature
+            .value
+            .unwrap_or_else(|| period_celsius(period));
+        let description = if observation.text_description.is_empty() {
+            period.short_forecast.clone()
+        } else {
+            observation.text_description.clone()
+        };
+        let feels_like = observation
+            .heat_index
+            .value
+            .or(observation.wind_chill.value)
+            .unwrap_or(temp);
+        let pressure = observation
+            .sea_level_pressure
+            .value
+            .or(observation.barometric_pressure.value)
+            .map(|pa| pa / 100.0)
+            .unwrap_or(0.0);
+        let (_, timezone) = parse_timestamp(&period.start_time).unwrap_or((0, 0));
+        let (dt, _) = parse_timestamp(&observation.timestamp).unwrap_or((0, 0));
+        let (name, state) = match point.relative_location {
+            Some(location) => (location.properties.city, location.properties.state),
+            None => (String::new(), String::new()),
+        };
+
+        Ok(WeatherReport {
+            coord: Coord { lat, lon },
+            weather: vec![classify_short_forecast(&description)],
+            main: MainReadings {
+                temp,
+                feels_like,
+                temp_min: temp,
+                temp_max: temp,
+                pressure,
+                humidity: observation.relative_humidity.value.unwrap_or(0.0),
+                sea_level: observation.sea_level_pressure.value.map(|pa| pa / 100.0),
+                grnd_level: observation.barometric_pressure.value.map(|pa| pa / 100.0),
+            },
+            visibility: observation.visibility.value.map(|m| m.round() as i64),
+            wind: Wind {
+                speed: observation.wind_speed.speed().unwrap_or(0.0),
+                deg: observation.wind_direction.value.unwrap_or(0.0),
+                gust: observation.wind_gust.speed(),
+            },
+            clouds: Clouds {
+                all: observation
+                    .cloud_layers
+                    .iter()
+                    .map(|layer| cloud_amount_percent(&layer.amount))
+                    .max()
+                    .unwrap_or(0),
+            },
+            rain: None,
+            snow: None,
+            dt,
+            sys: Sys {
+                country: if state.is_empty() {
+                    "US".to_string()
+                } else {
+                    format!("US-{}", state)
+                },
+                sunrise: 0,
+                sunset: 0,
+            },
+            timezone,
+            name,
+            stale_since: None,
+        })
+    }
+
+    fn forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        let (lat, lon) = self.resolve(location)?;
+        let point = self.point(lat, lon)?;
+        let periods = self.periods(&point, true)?;
+        let timezone = periods
+            .first()
+            .and_then(|period| parse_timestamp(&period.start_time))
+            .map(|(_, offset)| offset)
+            .unwrap_or(0);
+
+        let list = periods
+            .iter()
+            .step_by(FORECAST_STEP_HOURS)
+            .map(|period| {
+                let temp = period_celsius(period);
+                ForecastSlot {
+                    dt: parse_timestamp(&period.start_time)
+                        .map(|(dt, _)| dt)
+                        .unwrap_or(0),
+                    main: MainReadings {
+                        temp,
+                        feels_like: temp,
+                        temp_min: temp,
+                        temp_max: temp,
+                        humidity: period
+                            .relative_humidity
+                            .as_ref()
+                            .and_then(|m| m.value)
+                            .unwrap_or(0.0),
+                        ..MainReadings::default()
+                    },
+                    weather: vec![classify_short_forecast(&period.short_forecast)],
+                    clouds: Clouds::default(),
+                    wind: Wind {
+                        speed: period
+                            .wind_speed
+                            .as_deref()
+                            .map(parse_wind_speed)
+                            .unwrap_or(0.0),
+                        deg: period
+                            .wind_direction
+                            .as_deref()
+                            .and_then(wind::compass_degrees)
+                            .unwrap_or(0.0),
+                        gust: None,
+                    },
+                    visibility: None,
+                    pop: period
+                        .probability_of_precipitation
+                        .as_ref()
+                        .and_then(|m| m.value)
+                        .unwrap_or(0.0)
+                        / 100.0,
+                    rain: None,
+                    snow: None,
+                    dt_txt: period.start_time.clone(),
+                }
+            })
+            .collect();
+
+        let name = point
+            .relative_location
+            .map(|location| location.properties.city)
+            .unwrap_or_default();
+
+        Ok(Forecast {
+            list,
+            city: ForecastCity {
+                name,
+                coord: Coord { lat, lon },
+                country: "US".to_string(),
+                timezone,
+                sunrise: 0,
+                sunset: 0,
+            },
+        })
+    }
+
+    fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError> {
+        Ok(self
+            .geocoder
+            .geocode(query)?
+            .into_iter()
+            .filter(|place| place.country == "US")
+            .collect())
+    }
+}
+
+/// The function that classifies NWS forecast text such as "Chance Showers And Thunderstorms" into the
+/// matching OpenWeatherMap condition, so it is drawn with the same rain, thunderstorm and snow scenes.
+/// The most severe weather mentioned in the text wins.
+pub fn classify_short_forecast(text: &str) -> WeatherCondition {
+    let lower = text.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| lower.contains(word));
+    let light = has(&["light", "slight chance", "isolated", "patchy", "sprinkles"]);
+    let heavy = has(&["heavy", "severe", "blizzard", "torrential"]);
+
+    let (id, main) = if has(&["tornado", "funnel cloud"]) {
+        (781, "Tornado")
+    } else if has(&["thunder", "t-storm", "tstm"]) {
+        if heavy {
+            (202, "Thunderstorm")
+        } else if light || lower.contains("chance") {
+            (200, "Thunderstorm")
+        } else {
+            (201, "Thunderstorm")
+        }
+    } else if has(&["freezing rain", "freezing drizzle"]) {
+        (511, "Rain")
+    } else if has(&["sleet", "ice pellets"]) {
+        (611, "Snow")
+    } else if has(&["rain and snow", "snow and rain", "wintry mix"]) {
+        (616, "Snow")
+    } else if has(&["snow", "flurries", "blizzard"]) {
+        if heavy {
+            (602, "Snow")
+        } else if light || lower.contains("flurries") {
+            (600, "Snow")
+        } else if lower.contains("showers") {
+            (621, "Snow")
+        } else {
+            (601, "Snow")
+        }
+    } else if lower.contains("drizzle") {
+        if heavy {
+            (302, "Drizzle")
+        } else {
+            (300, "Drizzle")
+        }
+    } else if has(&["rain", "showers", "sprinkles"]) {
+        if heavy {
+            (502, "Rain")
+        } else if light {
+            (500, "Rain")
+        } else if lower.contains("showers") {
+            (521, "Rain")
+        } else {
+            (501, "Rain")
+        }
+    } else if lower.contains("fog") {
+        (741, "Fog")
+    } else if lower.contains("smoke") {
+        (711, "Smoke")
+    } else if lower.contains("haze") {
+        (721, "Haze")
+    } else if lower.contains("sand") {
+        (751, "Sand")
+    } else if lower.contains("dust") {
+        (761, "Dust")
+    } else if lower.contains("mist") {
+        (701, "Mist")
+    } else if has(&["mostly sunny", "mostly clear"]) {
+        (801, "Clouds")
+    } else if lower.contains("partly cloudy") {
+        (802, "Clouds")
+    } else if has(&["partly sunny", "mostly cloudy"]) {
+        (803, "Clouds")
+    } else if has(&["cloudy", "overcast"]) {
+        (804, "Clouds")
+    } else if has(&["sunny", "clear", "fair"]) {
+        (800, "Clear")
+    } else {
+        (0, "Unknown")
+    };
+
+    WeatherCondition {
+        id,
+        main: main.to_string(),
+        description: lower,
+        icon: String::new(),
+    }
+}
+
+/// The function that returns the forecast period temperature in degrees Celsius.
+fn period_celsius(period: &Period) -> f64 {
+    if period.temperature_unit == "F" {
+        (period.temperature - 32.0) * 5.0 / 9.0
+    } else {
+        period.temperature
+    }
+}
+
+/// The function that reads the top speed from NWS wind text such as "10 to 15 mph" in meters per second.
+fn parse_wind_speed(text: &str) -> f64 {
+    let mph = text
+        .split_whitespace()
+        .filter_map(|word| word.parse::<f64>().ok())
+        .next_back()
+        .unwrap_or(0.0);
+    mph * 0.44704
+}
+
+/// The function that parses an ISO 8601 timestamp such as "2023-11-14T15:00:00-06:00".
+/// The function returns the unix timestamp and the UTC offset in seconds.
+fn parse_timestamp(text: &str) -> Option<(i64, i64)> {
+    let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    let zone = text.get(19..)?;
+    let zone = zone.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = if zone.is_empty() || zone == "Z" {
+        0
+    } else {
+        let sign = if zone.starts_with('-') { -1 } else { 1 };
+        let hours = zone.get(1..3)?.parse::<i64>().ok()?;
+        let minutes = zone.get(4..6)?.parse::<i64>().ok()?;
+        sign * (hours * 3600 + minutes * 60)
+    };
+
+    // Days since the unix epoch for a date in the proleptic Gregorian calendar.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let local = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some((local - offset, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    fn provider() -> Nws {
+        Nws::new(
+            &mockito::server_url(),
+            OpenMeteo::new(&mockito::server_url(), &mockito::server_url()),
+        )
+    }
+
+    fn serve(path: &str, body: &str) -> mockito::Mock {
+        mock("GET", path)
+            .with_status(200)
+            .with_header("content-type", "application/geo+json")
+            .with_body(body)
+            .create()
+    }
+
+    #[test]
+    fn test_classify_short_forecast() {
+        let id = |text| classify_short_forecast(text).id;
+
+        assert_eq!(id("Chance Showers And Thunderstorms"), 200);
+        assert_eq!(id("Showers And Thunderstorms Likely"), 201);
+        assert_eq!(id("Severe Thunderstorms"), 202);
+        assert_eq!(id("Light Rain"), 500);
+        assert_eq!(id("Rain"), 501);
+        assert_eq!(id("Heavy Rain"), 502);
+        assert_eq!(id("Chance Rain Showers"), 521);
+        assert_eq!(id("Drizzle"), 300);
+        assert_eq!(id("Freezing Rain"), 511);
+        assert_eq!(id("Sleet"), 611);
+        assert_eq!(id("Rain And Snow"), 616);
+        assert_eq!(id("Light Snow"), 600);
+        assert_eq!(id("Snow Showers"), 621);
+        assert_eq!(id("Blizzard"), 602);
+        assert_eq!(id("Patchy Fog"), 741);
+        assert_eq!(id("Haze"), 721);
+        assert_eq!(id("Areas Of Smoke"), 711);
+        assert_eq!(id("Sunny"), 800);
+        assert_eq!(id("Mostly Sunny"), 801);
+        assert_eq!(id("Partly Cloudy"), 802);
+        assert_eq!(id("Mostly Cloudy"), 803);
+        assert_eq!(id("Cloudy"), 804);
+        assert_eq!(id("Breezy"), 0);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("2023-11-14T21:53:00+00:00"),
+            Some((1699998780, 0))
+        );
+        assert_eq!(
+            parse_timestamp("2023-11-14T15:00:00-06:00"),
+            Some((1699995600, -21600))
+        );
+        assert_eq!(parse_timestamp("not a time"), None);
+    }
+
+    #[test]
+    fn test_wind_text() {
+        assert!((parse_wind_speed("10 to 15 mph") - 6.7056).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_current_from_fixtures() {
+        let _points = serve(
+            "/points/36.1622,-86.7744",
+            include_str!("fixtures/nws/points.json"),
+        );
+        let _forecast = serve(
+            "/gridpoints/OHX/50,57/forecast",
+            include_str!("fixtures/nws/forecast.json"),
+        );
+        let _stations = serve(
+            "/gridpoints/OHX/50,57/stations",
+            include_str!("fixtures/nws/stations.json"),
+        );
+        let _observation = serve(
+            "/stations/KBNA/observations/latest",
+            include_str!("fixtures/nws/observation.json"),
+        );
+
+        let report = provider()
+            .current(&Location::Coords {
+                lat: 36.1622,
+                lon: -86.7744,
+            })
+            .unwrap();
+
+        assert_eq!(report.name, "Nashville");
+        assert_eq!(report.sys.country, "US-TN");
+        assert_eq!(report.temperature(), 22.2);
+        assert_eq!(report.weather_id(), 200);
+        assert_eq!(report.description(), "light thunderstorms and rain");
+        assert_eq!(report.clouds.all, 100);
+        assert_eq!(report.wind.deg, 200.0);
+        assert!((report.wind.speed - 6.2).abs() < 1e-9);
+        assert_eq!(report.main.pressure, 1012.9);
+        assert_eq!(report.visibility, Some(12870));
+        assert_eq!(report.timezone, -21600);
+        assert_eq!(report.dt, 1699998780);
+    }
+
+    #[test]
+    fn test_forecast_from_fixtures() {
+        let _points = serve(
+            "/points/36.1622,-86.7744",
+            include_str!("fixtures/nws/points.json"),
+        );
+        let _hourly = serve(
+            "/gridpoints/OHX/50,57/forecast/hourly",
+            include_str!("fixtures/nws/forecast_hourly.json"),
+        );
+
+        let forecast = provider()
+            .forecast(&Location::Coords {
+                lat: 36.1622,
+                lon: -86.7744,
+            })
+            .unwrap();
+
+        assert_eq!(forecast.city.name, "Nashville");
+        assert_eq!(forecast.city.timezone, -21600);
+        assert_eq!(forecast.list.len(), 3);
+        assert_eq!(forecast.list[0].weather[0].id, 201);
+        assert_eq!(forecast.list[1].weather[0].id, 500);
+        assert_eq!(forecast.list[2].weather[0].id, 741);
+        assert!((forecast.list[0].main.temp - 22.2222).abs() < 1e-3);
+        assert_eq!(forecast.list[0].pop, 0.6);
+        assert_eq!(forecast.list[1].wind.deg, 270.0);
+    }
+}
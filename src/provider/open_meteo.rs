@@ -0,0 +1,512 @@
+use super::{get_json, Location, Place, WeatherProvider, FORECAST_STEP_HOURS};
+use crate::error::WeatherError;
+use crate::report::{
+    Clouds, Coord, Forecast, ForecastCity, ForecastSlot, MainReadings, Sys, WeatherCondition,
+    WeatherReport, Wind,
+};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+/// The base URL of the Open-Meteo forecast API.
+pub const DEFAULT_FORECAST_URL: &str = "https://api.open-meteo.com";
+
+/// The base URL of the Open-Meteo geocoding API.
+pub const DEFAULT_GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com";
+
+/// The Open-Meteo backend.
+/// Open-Meteo does not need an API key, so it works on a fresh machine without any setup.
+pub struct OpenMeteo {
+    client: Client,
+    forecast_url: String,
+    geocoding_url: String,
+}
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    utc_offset_seconds: i64,
+    current: Option<CurrentBlock>,
+    hourly: Option<HourlyBlock>,
+    daily: Option<DailyBlock>,
+}
+
+#[derive(Deserialize)]
+struct CurrentBlock {
+    time: i64,
+    temperature_2m: f64,
+    #[serde(default)]
+    relative_humidity_2m: f64,
+    #[serde(default)]
+    apparent_temperature: f64,
+    weather_code: i64,
+    #[serde(default)]
+    cloud_cover: f64,
+    #[serde(default)]
+    pressure_msl: f64,
+    #[serde(default)]
+    wind_speed_10m: f64,
+    #[serde(default)]
+    wind_direction_10m: f64,
+    #[serde(default)]
+    wind_gusts_10m: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct HourlyBlock {
+    time: Vec<i64>,
+    temperature_2m: Vec<f64>,
+    weather_code: Vec<i64>,
+    #[serde(default)]
+    relative_humidity_2m: Vec<f64>,
+    #[serde(default)]
+    apparent_temperature: Vec<f64>,
+    #[serde(default)]
+    cloud_cover: Vec<f64>,
+    #[serde(default)]
+    wind_speed_10m: Vec<f64>,
+    #[serde(default)]
+    wind_direction_10m: Vec<f64>,
+    #[serde(default)]
+    precipitation_probability: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct DailyBlock {
+    #[serde(default)]
+    sunrise: Vec<i64>,
+    #[serde(default)]
+    sunset: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Deserialize)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    country_code: String,
+    #[serde(default)]
+    admin1: Option<String>,
+}
+
+impl Default for OpenMeteo {
+    fn default() -> OpenMeteo {
+        OpenMeteo::new(DEFAULT_FORECAST_URL, DEFAULT_GEOCODING_URL)
+    }
+}
+
+impl OpenMeteo {
+    /// The function that creates the provider with the given forecast and geocoding base URLs.
+    pub fn new(forecast_url: &str, geocoding_url: &str) -> OpenMeteo {
+        OpenMeteo {
+            client: Client::new(),
+            forecast_url: forecast_url.trim_end_matches('/').to_string(),
+            geocoding_url: geocoding_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The function that turns the location into coordinates and a display name,
+    /// geocoding city names with the first matching place. Coordinates are named after themselves,
+    /// such as "35.01, 135.77", since Open-Meteo does not name the place.
+    fn resolve(&self, location: &Location) -> Result<(f64, f64, String, String), WeatherError> {
+        match location {
+            Location::Coords { lat, lon } => {
+                Ok((*lat, *lon, format!("{:.2}, {:.2}", lat, lon), String::new()))
+            }
+            Location::City(city) => {
+                let place = self
+                    .geocode(city)?
+                    .into_iter()
+                    .next()
+                    .ok_or(WeatherError::NotFound)?;
+                Ok((place.lat, place.lon, place.name, place.country))
+            }
+        }
+    }
+
+    /// The function that fetches the forecast endpoint for the coordinates with the given blocks.
+    fn fetch(
+        &self,
+        lat: f64,
+        lon: f64,
+        blocks: &[(&str, &str)],
+    ) -> Result<ForecastResponse, WeatherError> {
+        let url = format!("{}/v1/forecast", self.forecast_url);
+        let request = self
+            .client
+            .get(&url)
+            .query(&[
+                ("latitude", lat.to_string()),
+                ("longitude", lon.to_string()),
+            ])
+            .query(blocks)
+            .query(&[
+                ("daily", "sunrise,sunset"),
+                ("wind_speed_unit", "ms"),
+                ("timezone", "auto"),
+                ("timeformat", "unixtime"),
+            ]);
+
+        get_json(request)
+    }
+}
+
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "Open-Meteo"
+    }
+
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let (lat, lon, name, country) = self.resolve(location)?;
+        let response = self.fetch(
+            lat,
+            lon,
+            &[(
+                "current",
+                "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,\
+                 cloud_cover,pressure_msl,wind_speed_10m,wind_direction_10m,wind_gusts_10m",
+            )],
+        )?;
+        let current = response
+            .current
+            .ok_or(WeatherError::MissingField("current"))?;
+        let (sunrise, sunset) = match &response.daily {
+            Some(daily) => (
+                daily.sunrise.first().copied().unwrap_or(0),
+                daily.sunset.first().copied().unwrap_or(0),
+            ),
+            None => (0, 0),
+        };
+
+        Ok(WeatherReport {
+            coord: Coord {
+                lat: response.latitude,
+                lon: response.longitude,
+            },
+            weather: vec![wmo_condition(current.weather_code)],
+            main: MainReadings {
+                temp: current.temperature_2m,
+                feels_like: current.apparent_temperature,
+                temp_min: current.temperature_2m,
+                temp_max: current.temperature_2m,
+                pressure: current.pressure_msl,
+                humidity: current.relative_humidity_2m,
+                sea_level: Some(current.pressure_msl),
+                grnd_level: None,
+            },
+            visibility: None,
+            wind: Wind {
+                speed: current.wind_speed_10m,
+                deg: current.wind_direction_10m,
+                gust: current.wind_gusts_10m,
+            },
+            clouds: Clouds {
+                all: current.cloud_cover.round() as i64,
+            },
+            rain: None,
+            snow: None,
+            dt: current.time,
+            sys: Sys {
+                country,
+                sunrise,
+                sunset,
+            },
+            timezone: response.utc_offset_seconds,
+            name,
+            stale_since: None,
+        })
+    }
+
+    fn forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        let (lat, lon, name, country) = self.resolve(location)?;
+        let response = self.fetch(
+            lat,
+            lon,
+            &[
+                (
+                    "hourly",
+                    "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,\
+                     cloud_cover,wind_speed_10m,wind_direction_10m,precipitation_probability",
+                ),
+                ("forecast_days", "5"),
+            ],
+        )?;
+        let hourly = response
+            .hourly
+            .ok_or(WeatherError::MissingField("hourly"))?;
+
+        let list = (0..hourly.time.len())
+            .step_by(FORECAST_STEP_HOURS)
+            .filter_map(|i| {
+                let temp = *hourly.temperature_2m.get(i)?;
+                let code = *hourly.weather_code.get(i)?;
+                Some(ForecastSlot {
+                    dt: hourly.time[i],
+                    main: MainReadings {
+                        temp,
+                        feels_like: hourly.apparent_temperature.get(i).copied().unwrap_or(temp),
+                        temp_min: temp,
+                        temp_max: temp,
+                        humidity: hourly.relative_humidity_2m.get(i).copied().unwrap_or(0.0),
+                        ..MainReadings::default()
+                    },
+                    weather: vec![wmo_condition(code)],
+                    clouds: Clouds {
+                        all: hourly.cloud_cover.get(i).copied().unwrap_or(0.0).round() as i64,
+                    },
+                    wind: Wind {
+                        speed: hourly.wind_speed_10m.get(i).copied().unwrap_or(0.0),
+                        deg: hourly.wind_direction_10m.get(i).copied().unwrap_or(0.0),
+                        gust: None,
+                    },
+                    visibility: None,
+                    pop: hourly
+                        .precipitation_probability
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .unwrap_or(0.0)
+                        / 100.0,
+                    rain: None,
+                    snow: None,
+                    dt_txt: String::new(),
+                })
+            })
+            .collect();
+
+        let (sunrise, sunset) = match &response.daily {
+            Some(daily) => (
+                daily.sunrise.first().copied().unwrap_or(0),
+                daily.sunset.first().copied().unwrap_or(0),
+            ),
+            None => (0, 0),
+        };
+
+        Ok(Forecast {
+            list,
+            city: ForecastCity {
+                name,
+                coord: Coord {
+                    lat: response.latitude,
+                    lon: response.longitude,
+                },
+                country,
+                timezone: response.utc_offset_seconds,
+                sunrise,
+                sunset,
+            },
+        })
+    }
+
+    fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError> {
+        let url = format!("{}/v1/search", self.geocoding_url);
+        let request =
+            self.client
+                .get(&url)
+                .query(&[("name", query), ("count", "5"), ("format", "json")]);
+        let response: GeocodingResponse = get_json(request)?;
+
+        Ok(response
+            .results
+            .into_iter()
+            .map(|result| Place {
+                name: result.name,
+                lat: result.latitude,
+                lon: result.longitude,
+                country: result.country_code,
+                state: result.admin1,
+            })
+            .collect())
+    }
+}
+
+/// The function that maps a WMO weather interpretation code onto the matching OpenWeatherMap condition,
+/// so that Open-Meteo reports are drawn with the same scenes as OpenWeatherMap reports.
+pub fn wmo_condition(code: i64) -> WeatherCondition {
+    let (id, main, description) = match code {
+        0 => (800, "Clear", "clear sky"),
+        1 => (801, "Clouds", "mainly clear"),
+        2 => (802, "Clouds", "partly cloudy"),
+        3 => (804, "Clouds", "overcast"),
+        45 => (741, "Fog", "fog"),
+        48 => (741, "Fog", "depositing rime fog"),
+        51 => (300, "Drizzle", "light drizzle"),
+        53 => (301, "Drizzle", "moderate drizzle"),
+        55 => (302, "Drizzle", "dense drizzle"),
+        56 => (511, "Rain", "light freezing drizzle"),
+        57 => (511, "Rain", "dense freezing drizzle"),
+        61 => (500, "Rain", "slight rain"),
+        63 => (501, "Rain", "moderate rain"),
+        65 => (502, "Rain", "heavy rain"),
+        66 => (511, "Rain", "light freezing rain"),
+        67 => (511, "Rain", "heavy freezing rain"),
+        71 => (600, "Snow", "slight snow fall"),
+        73 => (601, "Snow", "moderate snow fall"),
+        75 => (602, "Snow", "heavy snow fall"),
+        77 => (600, "Snow", "snow grains"),
+        80 => (520, "Rain", "slight rain showers"),
+        81 => (521, "Rain", "moderate rain showers"),
+        82 => (522, "Rain", "violent rain showers"),
+        85 => (620, "Snow", "slight snow showers"),
+        86 => (622, "Snow", "heavy snow showers"),
+        95 => (211, "Thunderstorm", "thunderstorm"),
+        96 => (201, "Thunderstorm", "thunderstorm with slight hail"),
+        99 => (202, "Thunderstorm", "thunderstorm with heavy hail"),
+        _ => (0, "Unknown", "unknown weather"),
+    };
+
+    WeatherCondition {
+        id,
+        main: main.to_string(),
+        description: description.to_string(),
+        icon: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn provider() -> OpenMeteo {
+        OpenMeteo::new(&mockito::server_url(), &mockito::server_url())
+    }
+
+    #[test]
+    fn test_wmo_condition_categories() {
+        let category = |code| wmo_condition(code).id / 100;
+
+        assert_eq!(wmo_condition(0).id, 800);
+        for code in [1, 2, 3] {
+            assert_eq!(wmo_condition(code).main, "Clouds");
+        }
+        for code in [45, 48] {
+            assert_eq!(category(code), 7);
+        }
+        for code in [51, 53, 55] {
+            assert_eq!(category(code), 3);
+        }
+        for code in [61, 63, 65, 66, 67, 80, 81, 82] {
+            assert_eq!(category(code), 5);
+        }
+        // Freezing drizzle glazes like freezing rain
+        for code in [56, 57, 66, 67] {
+            assert_eq!(wmo_condition(code).id, 511);
+        }
+        for code in [71, 73, 75, 77, 85, 86] {
+            assert_eq!(category(code), 6);
+        }
+        for code in [95, 96, 99] {
+            assert_eq!(category(code), 2);
+        }
+        assert_eq!(wmo_condition(42).id, 0);
+    }
+
+    #[test]
+    fn test_current_by_city() {
+        let _geo = mock("GET", "/v1/search")
+            .match_query(Matcher::UrlEncoded("name".into(), "Madrid".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"results": [{"name": "Madrid", "latitude": 40.4165, "longitude": -3.70256, "country_code": "ES", "admin1": "Madrid"}]}"#,
+            )
+            .create();
+        let _forecast = mock("GET", "/v1/forecast")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("latitude".into(), "40.4165".into()),
+                Matcher::Regex("current=".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "latitude": 40.4,
+                    "longitude": -3.7,
+                    "utc_offset_seconds": 7200,
+                    "current": {
+                        "time": 1700000000,
+                        "temperature_2m": 18.3,
+                        "relative_humidity_2m": 40,
+                        "apparent_temperature": 17.0,
+                        "weather_code": 63,
+                        "cloud_cover": 88,
+                        "pressure_msl": 1012.4,
+                        "wind_speed_10m": 4.2,
+                        "wind_direction_10m": 250
+                    },
+                    "daily": {"sunrise": [1699944000], "sunset": [1699980000]}
+                }"#,
+            )
+            .create();
+
+        let report = provider()
+            .current(&Location::City("Madrid".to_string()))
+            .unwrap();
+
+        assert_eq!(report.name, "Madrid");
+        assert_eq!(report.sys.country, "ES");
+        assert_eq!(report.temperature(), 18.3);
+        assert_eq!(report.weather_id(), 501);
+        assert_eq!(report.clouds.all, 88);
+        assert_eq!(report.wind.deg, 250.0);
+        assert_eq!(report.timezone, 7200);
+        assert_eq!(report.sys.sunset, 1699980000);
+    }
+
+    #[test]
+    fn test_unknown_city() {
+        let _geo = mock("GET", "/v1/search")
+            .match_query(Matcher::UrlEncoded("name".into(), "Nowhere".into()))
+            .with_status(200)
+            .with_body(r#"{"generationtime_ms": 0.5}"#)
+            .create();
+
+        let result = provider().current(&Location::City("Nowhere".to_string()));
+
+        assert!(matches!(result, Err(WeatherError::NotFound)));
+    }
+
+    #[test]
+    fn test_forecast_uses_three_hour_slots() {
+        let _forecast = mock("GET", "/v1/forecast")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("latitude".into(), "51.5".into()),
+                Matcher::Regex("hourly=".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "latitude": 51.5,
+                    "longitude": -0.12,
+                    "hourly": {
+                        "time": [0, 3600, 7200, 10800, 14400, 18000, 21600],
+                        "temperature_2m": [5, 6, 7, 8, 9, 10, 11],
+                        "weather_code": [0, 0, 0, 61, 61, 61, 95],
+                        "precipitation_probability": [0, 0, 0, 80, null, 80, 90]
+                    }
+                }"#,
+            )
+            .create();
+
+        let forecast = provider()
+            .forecast(&Location::Coords {
+                lat: 51.5,
+                lon: -0.12,
+            })
+            .unwrap();
+
+        assert_eq!(forecast.city.name, "51.50, -0.12");
+        assert_eq!(forecast.list.len(), 3);
+        assert_eq!(forecast.list[1].dt, 10800);
+        assert_eq!(forecast.list[1].weather[0].id, 500);
+        assert_eq!(forecast.list[1].pop, 0.8);
+        assert_eq!(forecast.list[2].main.temp, 11.0);
+    }
+}
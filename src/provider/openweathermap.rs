@@ -0,0 +1,202 @@
+use super::{get_json, Location, Place, WeatherProvider};
+use crate::error::WeatherError;
+use crate::report::{Forecast, WeatherReport};
+use reqwest::blocking::Client;
+use std::env;
+
+/// The base URL of the OpenWeatherMap API.
+pub const DEFAULT_BASE_URL: &str = "https://api.openweathermap.org";
+
+/// The OpenWeatherMap backend.
+/// It needs an API key, which is read from the `API_KEY` environment variable by `from_env`.
+pub struct OpenWeatherMap {
+    client: Client,
+    api_key: Option<String>,
+    base_url: String,
+}
+
+impl OpenWeatherMap {
+    /// The function that creates the provider with the given API key and base URL.
+    pub fn new(api_key: Option<String>, base_url: &str) -> OpenWeatherMap {
+        OpenWeatherMap {
+            client: Client::new(),
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The function that creates the provider using the `API_KEY` from the environment.
+    /// A missing key is only reported once a request is made, so the window can show the error.
+    pub fn from_env() -> OpenWeatherMap {
+        OpenWeatherMap::new(env::var("API_KEY").ok(), DEFAULT_BASE_URL)
+    }
+
+    /// The function that builds a request to the given path with the location and API key as query parameters.
+    fn request(
+        &self,
+        path: &str,
+        location: &Location,
+    ) -> Result<reqwest::blocking::RequestBuilder, WeatherError> {
+        let api_key = self.api_key.as_ref().ok_or(WeatherError::MissingApiKey)?;
+        let url = format!("{}{}", self.base_url, path);
+
+        let request = match location {
+            Location::City(city) => self.client.get(&url).query(&[("q", city.as_str())]),
+            Location::Coords { lat, lon } => self
+                .client
+                .get(&url)
+                .query(&[("lat", lat.to_string()), ("lon", lon.to_string())]),
+        };
+
+        Ok(request.query(&[("appid", api_key.as_str()), ("units", "metric")]))
+    }
+}
+
+impl WeatherProvider for OpenWeatherMap {
+    fn name(&self) -> &'static str {
+        "OpenWeatherMap"
+    }
+
+    fn current(&self, location: &Location) -> Result<WeatherReport, WeatherError> {
+        let report: WeatherReport = get_json(self.request("/data/2.5/weather", location)?)?;
+        if report.weather.is_empty() {
+            return Err(WeatherError::MissingField("weather"));
+        }
+        Ok(report)
+    }
+
+    fn forecast(&self, location: &Location) -> Result<Forecast, WeatherError> {
+        get_json(self.request("/data/2.5/forecast", location)?)
+    }
+
+    fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError> {
+        let api_key = self.api_key.as_ref().ok_or(WeatherError::MissingApiKey)?;
+        let url = format!("{}/geo/1.0/direct", self.base_url);
+        let request = self.client.get(&url).query(&[
+            ("q", query),
+            ("limit", "5"),
+            ("appid", api_key.as_str()),
+        ]);
+
+        get_json(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn provider() -> OpenWeatherMap {
+        OpenWeatherMap::new(Some("mock_api_key".to_string()), &mockito::server_url())
+    }
+
+    #[test]
+    fn test_current_by_coords() {
+        let _m = mock("GET", "/data/2.5/weather")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("lat".into(), "35.0116".into()),
+                Matcher::UrlEncoded("lon".into(), "135.768".into()),
+                Matcher::UrlEncoded("appid".into(), "mock_api_key".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{"main": {"temp": 21.5}, "weather": [{"id": 803, "description": "broken clouds"}], "name": "Kyoto"}"#,
+            )
+            .create();
+
+        let report = provider()
+            .current(&Location::Coords {
+                lat: 35.0116,
+                lon: 135.768,
+            })
+            .unwrap();
+
+        assert_eq!(report.name, "Kyoto");
+        assert_eq!(report.weather_id(), 803);
+    }
+
+    #[test]
+    fn test_current_not_found() {
+        let _m = mock("GET", "/data/2.5/weather")
+            .match_query(Matcher::UrlEncoded("q".into(), "Atlantis".into()))
+            .with_status(404)
+            .with_body(r#"{"cod": "404", "message": "city not found"}"#)
+            .create();
+
+        let result = provider().current(&Location::City("Atlantis".to_string()));
+
+        assert!(matches!(result, Err(WeatherError::NotFound)));
+    }
+
+    #[test]
+    fn test_missing_api_key() {
+        let provider = OpenWeatherMap::new(None, &mockito::server_url());
+        let result = provider.current(&Location::City("London".to_string()));
+
+        assert!(matches!(result, Err(WeatherError::MissingApiKey)));
+    }
+
+    #[test]
+    fn test_forecast() {
+        let _m = mock("GET", "/data/2.5/forecast")
+            .match_query(Matcher::UrlEncoded("q".into(), "Madrid".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"list": [{"dt": 1700006400, "main": {"temp": 14.0}, "weather": [{"id": 800, "description": "clear sky"}]}], "city": {"name": "Madrid"}}"#,
+            )
+            .create();
+
+        let forecast = provider()
+            .forecast(&Location::City("Madrid".to_string()))
+            .unwrap();
+
+        assert_eq!(forecast.city.name, "Madrid");
+        assert_eq!(forecast.list[0].weather[0].id, 800);
+    }
+
+    #[test]
+    fn test_geocode() {
+        let _m = mock("GET", "/geo/1.0/direct")
+            .match_query(Matcher::UrlEncoded("q".into(), "Nashville".into()))
+            .with_status(200)
+            .with_body(
+                r#"[{"name": "Nashville", "lat": 36.1622, "lon": -86.7744, "country": "US", "state": "Tennessee"}]"#,
+            )
+            .create();
+
+        let places = provider().geocode("Nashville").unwrap();
+
+        assert_eq!(places.len(), 1);
+        assert_eq!(places[0].country, "US");
+        assert_eq!(places[0].state.as_deref(), Some("Tennessee"));
+        assert_eq!(places[0].label(), "Nashville, Tennessee, US");
+    }
+
+    #[test]
+    fn test_geocode_candidates() {
+        let _m = mock("GET", "/geo/1.0/direct")
+            .match_query(Matcher::UrlEncoded("q".into(), "São Paulo".into()))
+            .with_status(200)
+            .with_body(
+                r#"[
+                    {"name": "São Paulo", "lat": -23.5506, "lon": -46.6333, "country": "BR", "state": "São Paulo"},
+                    {"name": "São Paulo", "lat": 38.1833, "lon": -8.0833, "country": "PT"}
+                ]"#,
+            )
+            .create();
+
+        let places = provider().geocode("São Paulo").unwrap();
+
+        assert_eq!(places.len(), 2);
+        assert_eq!(places[0].label(), "São Paulo, São Paulo, BR");
+        assert_eq!(places[1].label(), "São Paulo, PT");
+        assert_eq!(
+            places[1].location(),
+            Location::Coords {
+                lat: 38.1833,
+                lon: -8.0833
+            }
+        );
+    }
+}
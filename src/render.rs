@@ -0,0 +1,262 @@
+//! Rendering scenes into images without a window.
+//!
+//! The renderer creates its own graphics device, draws the scene into an offscreen texture and reads the
+//! pixels back, so images can be made from cron jobs and on machines without a display.
+
+use crate::error::WeatherError;
+use crate::export::{Format, FrameWriter};
+use crate::report::WeatherReport;
+use crate::scene::{self, SceneState};
+use crate::units::Units;
+use futures::executor::block_on;
+use nannou::draw::{self, Draw};
+use nannou::geom::Rect;
+use nannou::image::RgbaImage;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::SeedableRng;
+use nannou::wgpu;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// The longest time to wait for a rendered image to be read back from the graphics device.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The size of rendered images in pixels, the same as the window.
+pub const DEFAULT_SIZE: [u32; 2] = [1024, 512];
+
+/// The length and frame rate of an exported animation, and the size of its frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub seconds: f32,
+    pub fps: u32,
+    pub size: [u32; 2],
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            seconds: 3.0,
+            fps: 20,
+            size: DEFAULT_SIZE,
+        }
+    }
+}
+
+impl Animation {
+    /// The function that returns the number of frames in the animation, at least one.
+    pub fn frames(&self) -> u32 {
+        ((self.seconds * self.fps as f32).round() as u32).max(1)
+    }
+}
+
+/// A renderer that draws into an offscreen texture.
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    texture: wgpu::Texture,
+    renderer: draw::Renderer,
+    capturer: wgpu::TextureCapturer,
+}
+
+impl HeadlessRenderer {
+    /// The function that creates a renderer for images of the given width and height.
+    /// The function returns an error if there is no graphics adapter, not even a software one.
+    pub fn new(size: [u32; 2]) -> Result<HeadlessRenderer, WeatherError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::DEFAULT_POWER_PREFERENCE,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| WeatherError::Render("no graphics adapter was found".to_string()))?;
+        let (device, queue) =
+            block_on(adapter.request_device(&wgpu::default_device_descriptor(), None))
+                .map_err(|e| WeatherError::Render(e.to_string()))?;
+
+        let texture = wgpu::TextureBuilder::new()
+            .size(size)
+            .format(wgpu::TextureCapturer::DST_FORMAT)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+            .build(&device);
+        let renderer = draw::RendererBuilder::new()
+            .build_from_texture_descriptor(&device, texture.descriptor());
+
+        Ok(HeadlessRenderer {
+            device,
+            queue,
+            texture,
+            renderer,
+            capturer: wgpu::TextureCapturer::default(),
+        })
+    }
+
+    /// The function that returns the area the scene is drawn in, centered like a window.
+    pub fn rect(&self) -> Rect {
+        let [width, height] = self.texture.size();
+        Rect::from_w_h(width as f32, height as f32)
+    }
+
+    /// The function that loads an image as a texture that can be drawn by this renderer.
+    pub fn load_texture(&self, path: &Path) -> Result<wgpu::Texture, WeatherError> {
+        wgpu::Texture::from_path((&self.device, &self.queue), path)
+            .map_err(|e| WeatherError::Render(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The function that renders the drawing and returns the image.
+    pub fn render(&mut self, draw: &Draw) -> Result<RgbaImage, WeatherError> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("weather_visualizer_render"),
+            });
+        self.renderer
+            .render_to_texture(&self.device, &mut encoder, draw, &self.texture);
+        let snapshot = self
+            .capturer
+            .capture(&self.device, &mut encoder, &self.texture);
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        snapshot
+            .read(move |result| {
+                sender.send(result.map(|image| image.to_owned())).ok();
+            })
+            .map_err(|_| WeatherError::Render("reading the image timed out".to_string()))?;
+        self.capturer
+            .await_active_snapshots(&self.device)
+            .map_err(|_| WeatherError::Render("reading the image timed out".to_string()))?;
+
+        // The image is only mapped while the device is polled, which the capturer can stop doing before
+        // the read has started, so keep polling until the image arrives
+        let started = Instant::now();
+        loop {
+            self.device.poll(wgpu::Maintain::Wait);
+            match receiver.recv_timeout(Duration::from_millis(10)) {
+                Ok(image) => return image.map_err(|e| WeatherError::Render(e.to_string())),
+                Err(RecvTimeoutError::Timeout) if started.elapsed() < READ_TIMEOUT => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(WeatherError::Render(
+                        "reading the image timed out".to_string(),
+                    ))
+                }
+                Err(e) => return Err(WeatherError::Render(e.to_string())),
+            }
+        }
+    }
+}
+
+/// The function that draws the scene for the report over the city image, the same way the window does.
+/// The particles and clouds are placed as they would be on the first frame. The same seed always draws
+/// the same scene.
+pub fn draw_report(
+    draw: &Draw,
+    win: Rect,
+    texture: &wgpu::Texture,
+    report: &WeatherReport,
+    seed: u64,
+    units: Units,
+) {
+    let (state, mut rng) = SceneState::seeded(report, win, seed);
+    let mut frame_rng = StdRng::seed_from_u64(scene::frame_seed(&mut rng));
+    scene::draw_scene(draw, win, texture, report, &state, 0.0, &mut frame_rng);
+    scene::draw_weather_label(draw, win, report, units);
+}
+
+/// The function that renders the scene for the report over the city image and saves it as a PNG of the
+/// given width and height.
+pub fn render_to_file(
+    report: &WeatherReport,
+    city_image: &Path,
+    output: &Path,
+    size: [u32; 2],
+    seed: u64,
+    units: Units,
+) -> Result<(), WeatherError> {
+    let mut renderer = HeadlessRenderer::new(size)?;
+    let texture = renderer.load_texture(city_image)?;
+
+    let draw = Draw::new();
+    draw_report(&draw, renderer.rect(), &texture, report, seed, units);
+    let image = renderer.render(&draw)?;
+
+    image
+        .save(output)
+        .map_err(|e| WeatherError::Render(format!("{}: {}", output.display(), e)))
+}
+
+/// The function that renders the animated scene for the report over the city image and saves it in the
+/// format. The particles move and the clouds drift from frame to frame as they do in the window.
+pub fn render_animation(
+    report: &WeatherReport,
+    city_image: &Path,
+    output: &Path,
+    format: Format,
+    animation: Animation,
+    seed: u64,
+    units: Units,
+) -> Result<(), WeatherError> {
+    let mut renderer = HeadlessRenderer::new(animation.size)?;
+    let texture = renderer.load_texture(city_image)?;
+    let win = renderer.rect();
+
+    let (mut state, mut rng) = SceneState::seeded(report, win, seed);
+    let frames = animation.frames();
+    let dt = 1.0 / animation.fps.max(1) as f32;
+
+    let mut writer = FrameWriter::create(format, output, frames, animation.fps)?;
+    for frame in 0..frames {
+        let draw = Draw::new();
+        let time = frame as f32 * dt;
+        let mut frame_rng = StdRng::seed_from_u64(scene::frame_seed(&mut rng));
+        scene::draw_scene(&draw, win, &texture, report, &state, time, &mut frame_rng);
+        scene::draw_weather_label(&draw, win, report, units);
+        writer.write_frame(&renderer.render(&draw)?)?;
+        state.update(dt, win);
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animation_frames() {
+        assert_eq!(Animation::default().frames(), 60);
+        let short = Animation {
+            seconds: 0.01,
+            ..Animation::default()
+        };
+        assert_eq!(short.frames(), 1);
+    }
+
+    #[test]
+    fn test_render_to_file() {
+        let report: WeatherReport = serde_json::from_str(
+            r#"{"weather": [{"id": 500, "description": "light rain"}], "main": {"temp": 12.0}}"#,
+        )
+        .unwrap();
+        let output = std::env::temp_dir().join("weather_visualizer_test_render.png");
+
+        match render_to_file(
+            &report,
+            Path::new("src/assets/london.png"),
+            &output,
+            DEFAULT_SIZE,
+            1,
+            Units::Metric,
+        ) {
+            Ok(()) => {
+                let image = nannou::image::open(&output).unwrap().to_rgba8();
+                assert_eq!([image.width(), image.height()], DEFAULT_SIZE);
+                std::fs::remove_file(&output).ok();
+            }
+            // Machines without any graphics adapter cannot render at all
+            Err(WeatherError::Render(e)) if e.contains("adapter") => {
+                eprintln!("Skipping the render test: {}", e)
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
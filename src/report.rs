@@ -0,0 +1,344 @@
+use crate::condition::Condition;
+use serde::{Deserialize, Serialize};
+
+/// The current weather report for a city.
+/// This mirrors the payload returned by the OpenWeatherMap `/data/2.5/weather` endpoint so that
+/// the renderers can use every field the API provides instead of a handful of tuple values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeatherReport {
+    #[serde(default)]
+    pub coord: Coord,
+    #[serde(default)]
+    pub weather: Vec<WeatherCondition>,
+    pub main: MainReadings,
+    #[serde(default)]
+    pub visibility: Option<i64>,
+    #[serde(default)]
+    pub wind: Wind,
+    #[serde(default)]
+    pub clouds: Clouds,
+    #[serde(default)]
+    pub rain: Option<Precipitation>,
+    #[serde(default)]
+    pub snow: Option<Precipitation>,
+    #[serde(default)]
+    pub dt: i64,
+    #[serde(default)]
+    pub sys: Sys,
+    #[serde(default)]
+    pub timezone: i64,
+    #[serde(default)]
+    pub name: String,
+    /// The time the report was fetched, as a unix timestamp, when the API could not be reached and the
+    /// last cached report is shown instead.
+    #[serde(skip)]
+    pub stale_since: Option<i64>,
+}
+
+/// The coordinates of the city the report was made for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coord {
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// A single weather condition reported for the city, such as "light rain" with the id 500.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeatherCondition {
+    pub id: i64,
+    #[serde(default)]
+    pub main: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub icon: String,
+}
+
+/// The main temperature, pressure and humidity readings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MainReadings {
+    pub temp: f64,
+    #[serde(default)]
+    pub feels_like: f64,
+    #[serde(default)]
+    pub temp_min: f64,
+    #[serde(default)]
+    pub temp_max: f64,
+    #[serde(default)]
+    pub pressure: f64,
+    #[serde(default)]
+    pub humidity: f64,
+    #[serde(default)]
+    pub sea_level: Option<f64>,
+    #[serde(default)]
+    pub grnd_level: Option<f64>,
+}
+
+/// The wind speed in meters per second and the direction it blows from in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wind {
+    #[serde(default)]
+    pub speed: f64,
+    #[serde(default)]
+    pub deg: f64,
+    #[serde(default)]
+    pub gust: Option<f64>,
+}
+
+/// The cloud coverage as a percentage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Clouds {
+    #[serde(default)]
+    pub all: i64,
+}
+
+/// The amount of rain or snow in millimeters over the last one and three hours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Precipitation {
+    #[serde(rename = "1h", default)]
+    pub one_hour: Option<f64>,
+    #[serde(rename = "3h", default)]
+    pub three_hours: Option<f64>,
+}
+
+/// The country and the sunrise and sunset times of the city as unix timestamps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sys {
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub sunrise: i64,
+    #[serde(default)]
+    pub sunset: i64,
+}
+
+/// The 5 day forecast for a city in 3 hour slots.
+/// This mirrors the payload returned by the OpenWeatherMap `/data/2.5/forecast` endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Forecast {
+    #[serde(default)]
+    pub list: Vec<ForecastSlot>,
+    #[serde(default)]
+    pub city: ForecastCity,
+}
+
+/// The forecast weather for one 3 hour slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForecastSlot {
+    pub dt: i64,
+    pub main: MainReadings,
+    #[serde(default)]
+    pub weather: Vec<WeatherCondition>,
+    #[serde(default)]
+    pub clouds: Clouds,
+    #[serde(default)]
+    pub wind: Wind,
+    #[serde(default)]
+    pub visibility: Option<i64>,
+    /// The probability of precipitation from 0 to 1.
+    #[serde(default)]
+    pub pop: f64,
+    #[serde(default)]
+    pub rain: Option<Precipitation>,
+    #[serde(default)]
+    pub snow: Option<Precipitation>,
+    #[serde(default)]
+    pub dt_txt: String,
+}
+
+/// The city a forecast was made for.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ForecastCity {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub coord: Coord,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub timezone: i64,
+    #[serde(default)]
+    pub sunrise: i64,
+    #[serde(default)]
+    pub sunset: i64,
+}
+
+impl Forecast {
+    /// The function that turns every forecast slot into a weather report for the forecast city,
+    /// so a slot can be drawn the same way as the current weather.
+    pub fn reports(&self) -> Vec<WeatherReport> {
+        self.list
+            .iter()
+            .map(|slot| WeatherReport {
+                coord: self.city.coord,
+                weather: slot.weather.clone(),
+                main: slot.main.clone(),
+                visibility: slot.visibility,
+                wind: slot.wind,
+                clouds: slot.clouds,
+                rain: slot.rain,
+                snow: slot.snow,
+                dt: slot.dt,
+                sys: Sys {
+                    country: self.city.country.clone(),
+                    sunrise: self.city.sunrise,
+                    sunset: self.city.sunset,
+                },
+                timezone: self.city.timezone,
+                name: self.city.name.clone(),
+                stale_since: None,
+            })
+            .collect()
+    }
+}
+
+impl WeatherReport {
+    /// The function that returns the temperature of the report in degrees Celsius.
+    pub fn temperature(&self) -> f64 {
+        self.main.temp
+    }
+
+    /// The function that returns the id of the first reported weather condition, or 0 if there is none.
+    pub fn weather_id(&self) -> i64 {
+        self.weather.first().map(|w| w.id).unwrap_or(0)
+    }
+
+    /// The function that returns the description of the first reported weather condition.
+    pub fn description(&self) -> &str {
+        self.weather
+            .first()
+            .map(|w| w.description.as_str())
+            .unwrap_or("")
+    }
+
+    /// The function that classifies every reported weather condition, without duplicates.
+    /// Reports without any condition are classified as `Condition::Unknown(0)`.
+    pub fn conditions(&self) -> Vec<Condition> {
+        if self.weather.is_empty() {
+            return vec![Condition::Unknown(0)];
+        }
+
+        let mut conditions = Vec::new();
+        for weather in &self.weather {
+            let condition = Condition::from_owm_id(weather.id);
+            if !conditions.contains(&condition) {
+                conditions.push(condition);
+            }
+        }
+        conditions
+    }
+
+    /// The function that returns the descriptions of every reported weather condition.
+    pub fn descriptions(&self) -> Vec<&str> {
+        self.weather
+            .iter()
+            .map(|w| w.description.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_forecast() {
+        let json = r#"{
+            "cod": "200",
+            "cnt": 2,
+            "list": [
+                {
+                    "dt": 1700006400,
+                    "main": {"temp": 9.5, "feels_like": 7.1, "pressure": 1006, "humidity": 80},
+                    "weather": [{"id": 801, "main": "Clouds", "description": "few clouds", "icon": "02n"}],
+                    "clouds": {"all": 20},
+                    "wind": {"speed": 3.2, "deg": 200},
+                    "visibility": 10000,
+                    "pop": 0,
+                    "dt_txt": "2023-11-15 00:00:00"
+                },
+                {
+                    "dt": 1700017200,
+                    "main": {"temp": 8.1},
+                    "weather": [{"id": 500, "main": "Rain", "description": "light rain", "icon": "10n"}],
+                    "pop": 0.64,
+                    "rain": {"3h": 1.2},
+                    "dt_txt": "2023-11-15 03:00:00"
+                }
+            ],
+            "city": {"name": "London", "coord": {"lat": 51.5085, "lon": -0.1257}, "country": "GB", "timezone": 0}
+        }"#;
+
+        let forecast: Forecast = serde_json::from_str(json).unwrap();
+
+        assert_eq!(forecast.city.name, "London");
+        assert_eq!(forecast.list.len(), 2);
+        assert_eq!(forecast.list[0].clouds.all, 20);
+        assert_eq!(forecast.list[1].pop, 0.64);
+        assert_eq!(forecast.list[1].rain.unwrap().three_hours, Some(1.2));
+
+        let reports = forecast.reports();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].name, "London");
+        assert_eq!(reports[1].dt, 1700017200);
+        assert_eq!(reports[1].temperature(), 8.1);
+        assert_eq!(reports[1].weather_id(), 500);
+        assert_eq!(reports[1].sys.country, "GB");
+        assert_eq!(reports[1].coord.lat, 51.5085);
+    }
+
+    #[test]
+    fn test_deserialize_full_payload() {
+        let json = r#"{
+            "coord": {"lon": -0.1257, "lat": 51.5085},
+            "weather": [
+                {"id": 500, "main": "Rain", "description": "light rain", "icon": "10d"},
+                {"id": 701, "main": "Mist", "description": "mist", "icon": "50d"}
+            ],
+            "base": "stations",
+            "main": {
+                "temp": 11.2,
+                "feels_like": 10.4,
+                "temp_min": 9.9,
+                "temp_max": 12.3,
+                "pressure": 1004,
+                "humidity": 87
+            },
+            "visibility": 8000,
+            "wind": {"speed": 5.66, "deg": 230, "gust": 9.1},
+            "clouds": {"all": 75},
+            "rain": {"1h": 0.42},
+            "dt": 1700000000,
+            "sys": {"type": 2, "id": 2075535, "country": "GB", "sunrise": 1699945000, "sunset": 1699978000},
+            "timezone": 0,
+            "id": 2643743,
+            "name": "London",
+            "cod": 200
+        }"#;
+
+        let report: WeatherReport = serde_json::from_str(json).unwrap();
+
+        assert_eq!(report.name, "London");
+        assert_eq!(report.temperature(), 11.2);
+        assert_eq!(report.weather_id(), 500);
+        assert_eq!(report.description(), "light rain");
+        assert_eq!(report.weather.len(), 2);
+        assert_eq!(report.main.humidity, 87.0);
+        assert_eq!(report.visibility, Some(8000));
+        assert_eq!(report.wind.deg, 230.0);
+        assert_eq!(report.wind.gust, Some(9.1));
+        assert_eq!(report.clouds.all, 75);
+        assert_eq!(report.rain.unwrap().one_hour, Some(0.42));
+        assert_eq!(report.sys.country, "GB");
+        assert_eq!(report.sys.sunset, 1699978000);
+        assert_eq!(report.coord.lat, 51.5085);
+        assert_eq!(
+            report.conditions(),
+            vec![
+                Condition::Rain(crate::condition::Intensity::Light),
+                Condition::Mist
+            ]
+        );
+        assert_eq!(report.descriptions(), vec!["light rain", "mist"]);
+    }
+}
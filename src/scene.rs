@@ -0,0 +1,973 @@
+//! Drawing of the weather scenes.
+//!
+//! A scene is built from every condition in the report. The conditions are first turned into
+//! `Layers`, so that rain over mist or snow in fog are drawn together, and the layers are then
+//! drawn back to front over the city texture.
+
+use crate::astronomy::MoonPhase;
+use crate::clouds::CloudField;
+use crate::condition::{CloudCover, Condition};
+use crate::daylight::{Daylight, Phase, MAX_ELEVATION};
+use crate::error::WeatherError;
+use crate::particles::ParticleSystem;
+use crate::report::{WeatherReport, Wind};
+use crate::units::Units;
+use crate::wind;
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+
+/// The layers of a scene, from back to front.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layers {
+    /// Whether the sky is clear enough to see the sun, or the moon and stars at night.
+    pub sun: bool,
+    /// The cloud percentage the conditions ask for and whether they are dark rain clouds.
+    pub clouds: Option<(i32, bool)>,
+    /// Particles hanging in the air, such as mist, smoke or dust, with their color and count.
+    pub haze: Vec<(Srgb<u8>, i32)>,
+    /// Rain drops with their color and count.
+    pub rain: Vec<(Srgb<u8>, i32)>,
+    /// The number of sleet pellets.
+    pub sleet: i32,
+    /// The number of snow flakes.
+    pub snow: i32,
+    pub lightning: bool,
+    pub squalls: bool,
+    pub tornado: bool,
+    /// Weather codes that have no visualization.
+    pub unknown: Vec<i64>,
+}
+
+impl Layers {
+    /// The function that combines the layers needed for every condition in the report.
+    /// The darkest and densest cloud cover of all conditions is used for the sky.
+    pub fn from_conditions(conditions: &[Condition]) -> Layers {
+        let mut layers = Layers::default();
+        let mut clear = false;
+
+        for condition in conditions {
+            match *condition {
+                Condition::Thunderstorm(intensity) => {
+                    let amount = intensity.pick(25, 50, 100);
+                    layers.add_clouds(amount, true);
+                    layers.rain.push((BLUE, amount));
+                    layers.lightning = true;
+                }
+                Condition::Drizzle(intensity) => {
+                    let amount = intensity.pick(5, 10, 20);
+                    layers.add_clouds(amount, true);
+                    layers.rain.push((BLUE, amount));
+                }
+                Condition::Rain(intensity) => {
+                    let amount = intensity.pick(25, 50, 100);
+                    layers.add_clouds(amount, true);
+                    layers.rain.push((BLUE, amount));
+                }
+                Condition::FreezingRain => {
+                    layers.add_clouds(50, true);
+                    layers.rain.push((LIGHTCYAN, 50));
+                }
+                Condition::Snow(intensity) => layers.snow += intensity.pick(50, 150, 300),
+                Condition::Sleet(intensity) => {
+                    layers.add_clouds(10, true);
+                    layers.rain.push((BLUE, 10));
+                    layers.sleet += intensity.pick(50, 100, 200);
+                }
+                Condition::Mist => layers.haze.push((LIGHTGRAY, 2000)),
+                Condition::Haze => layers.haze.push((LIGHTGRAY, 1000)),
+                Condition::Fog => layers.haze.push((WHITESMOKE, 4000)),
+                Condition::Smoke => layers.haze.push((DARKGRAY, 2000)),
+                Condition::Dust => layers.haze.push((BURLYWOOD, 2000)),
+                Condition::Sand => layers.haze.push((SANDYBROWN, 2000)),
+                Condition::Ash => layers.haze.push((GRAY, 2000)),
+                Condition::Squall => layers.squalls = true,
+                Condition::Tornado => {
+                    layers.squalls = true;
+                    layers.tornado = true;
+                }
+                Condition::Clear => clear = true,
+                Condition::Clouds(cover) => {
+                    let amount = match cover {
+                        CloudCover::Few => 10,
+                        CloudCover::Scattered => 50,
+                        CloudCover::Broken => 75,
+                        CloudCover::Overcast => 100,
+                    };
+                    layers.add_clouds(amount, false);
+                }
+                Condition::Unknown(id) => layers.unknown.push(id),
+            }
+        }
+
+        // The sun shows through a clear sky or a few fair weather clouds, but never through rain clouds.
+        layers.sun = match layers.clouds {
+            Some((amount, rain)) => !rain && amount < 50,
+            None => clear,
+        };
+
+        layers
+    }
+
+    fn add_clouds(&mut self, amount: i32, rain: bool) {
+        self.clouds = match self.clouds {
+            Some((current, current_rain)) => Some((current.max(amount), current_rain || rain)),
+            None => Some((amount, rain)),
+        };
+    }
+}
+
+/// The parts of a scene that are built once for a report and then move from frame to frame: the rain,
+/// sleet, snow and haze particles and the clouds.
+#[derive(Default)]
+pub struct SceneState {
+    pub particles: ParticleSystem,
+    pub clouds: Option<CloudField>,
+}
+
+impl SceneState {
+    /// The function that builds the particles and clouds of the report's scene within the bounds.
+    /// Both are seeded from `rng`, so the same random numbers always build the same scene.
+    pub fn new(report: &WeatherReport, bounds: Rect, rng: &mut StdRng) -> SceneState {
+        let layers = Layers::from_conditions(&report.conditions());
+        let particles =
+            ParticleSystem::from_layers(&layers, bounds, wind::drift(&report.wind), rng.gen());
+        let (percent, dark) = cloud_percent(report, &layers);
+        let clouds = if percent > 0 {
+            Some(CloudField::new(percent, dark, rng.gen()))
+        } else {
+            None
+        };
+
+        SceneState { particles, clouds }
+    }
+
+    /// The function that builds the scene for the report from the seed and returns it with the random
+    /// numbers the seeds of its frames are drawn from, see `frame_seed`. The window and the renderer both
+    /// start their scenes here, so the same seed draws the same first frame in both.
+    pub fn seeded(report: &WeatherReport, bounds: Rect, seed: u64) -> (SceneState, StdRng) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let state = SceneState::new(report, bounds, &mut rng);
+        (state, rng)
+    }
+
+    /// The function that moves the particles `dt` seconds further within the bounds.
+    pub fn update(&mut self, dt: f32, bounds: Rect) {
+        self.particles.update(dt, bounds);
+    }
+}
+
+/// The function that returns the seed of the random numbers for drawing the next frame of a scene, drawn
+/// from the random numbers returned by `SceneState::seeded`.
+pub fn frame_seed(rng: &mut StdRng) -> u64 {
+    rng.gen()
+}
+
+/// The function that draws the full scene for the weather report: the temperature colored background,
+/// the city texture and a layer for every reported condition. The weather label is drawn over the scene
+/// with `draw_weather_label`.
+/// Rain, sleet, snow, haze and clouds come from the scene state, which should be built from the same report.
+/// The clouds drift with the wind for `time` seconds, and the sky follows the local time of the report.
+/// Lightning and squalls are placed with `rng`, so the same random numbers always draw the same scene.
+pub fn draw_scene(
+    draw: &Draw,
+    win: Rect,
+    texture: &wgpu::Texture,
+    report: &WeatherReport,
+    state: &SceneState,
+    time: f32,
+    rng: &mut StdRng,
+) {
+    let temp = get_temp_color(&report.temperature());
+    draw.texture(texture).wh(win.wh());
+    draw.background().color(temp);
+
+    let layers = Layers::from_conditions(&report.conditions());
+    let daylight = Daylight::from_report(report);
+
+    draw_sky(draw, win, &daylight);
+    if layers.sun {
+        let position = sky_position(win, &daylight);
+        if daylight.is_day() {
+            draw_clear_sky(draw, position);
+        } else {
+            draw_stars(draw, win, &daylight, time);
+            draw_moon(draw, position, &daylight.moon);
+        }
+    }
+    if let Some(clouds) = &state.clouds {
+        clouds.draw(draw, win, wind::drift(&report.wind) * time, time);
+    }
+    state.particles.draw(draw);
+    if layers.lightning {
+        draw_lightning(draw, win, rng);
+    }
+    if layers.squalls {
+        draw_squalls(draw, win, &report.wind, rng);
+    }
+    if layers.tornado {
+        draw_tornado(draw, win);
+    }
+    for id in &layers.unknown {
+        draw_unknown(draw, win, *id);
+    }
+}
+
+/// The function that returns the seed of the scene for the report, made from the city name and the time of
+/// the report. The same report always gets the same seed, so the scene can be drawn again exactly.
+pub fn scene_seed(report: &WeatherReport) -> u64 {
+    // FNV-1a, which unlike the standard library's hasher is the same on every platform and release
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in report.name.bytes().chain(report.dt.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The function that returns the cloud percentage to draw and whether the clouds are dark rain clouds.
+/// The reported `clouds.all` is used when there is one, and the amount the conditions ask for otherwise,
+/// since not every provider reports the cloud percentage.
+pub fn cloud_percent(report: &WeatherReport, layers: &Layers) -> (i64, bool) {
+    let (amount, dark) = layers.clouds.unwrap_or((0, false));
+    if report.clouds.all > 0 {
+        (report.clouds.all, dark)
+    } else {
+        (amount as i64, dark)
+    }
+}
+
+/// The height of the forecast timeline strip along the bottom of the window.
+pub const TIMELINE_HEIGHT: f32 = 50.0;
+
+/// The function that draws the forecast timeline strip along the bottom of the window.
+/// Every slot gets a small icon of its scene and the temperatures are joined into a curve tinted by
+/// `get_temp_color`. The selected slot is outlined and its local time is written above the strip.
+pub fn draw_timeline(draw: &Draw, win: Rect, reports: &[WeatherReport], selected: usize) {
+    if reports.is_empty() {
+        return;
+    }
+
+    let strip = Rect::from_x_y_w_h(
+        win.x(),
+        win.bottom() + TIMELINE_HEIGHT / 2.0,
+        win.w(),
+        TIMELINE_HEIGHT,
+    );
+    draw.rect()
+        .xy(strip.xy())
+        .wh(strip.wh())
+        .color(rgba(1.0, 1.0, 1.0, 0.6));
+
+    let cell_w = strip.w() / reports.len() as f32;
+    let icon_size = cell_w.min(24.0);
+    let cell_x = |i: usize| strip.left() + (i as f32 + 0.5) * cell_w;
+
+    for (i, report) in reports.iter().enumerate() {
+        let layers = Layers::from_conditions(&report.conditions());
+        draw_icon(
+            draw,
+            pt2(cell_x(i), strip.top() - icon_size / 2.0 - 2.0),
+            icon_size,
+            &layers,
+        );
+    }
+
+    let temps: Vec<f64> = reports.iter().map(|r| r.temperature()).collect();
+    let heights = temperature_curve(&temps, strip.bottom() + 4.0, strip.bottom() + 18.0);
+    let points = temps
+        .iter()
+        .zip(heights)
+        .enumerate()
+        .map(|(i, (temp, y))| (pt2(cell_x(i), y), get_temp_color(temp)));
+    draw.polyline().weight(3.0).points_colored(points);
+
+    let selected = selected.min(reports.len() - 1);
+    draw.rect()
+        .x_y(cell_x(selected), strip.y())
+        .w_h(cell_w, strip.h())
+        .no_fill()
+        .stroke(BLACK)
+        .stroke_weight(2.0);
+
+    let report = &reports[selected];
+    draw.text(&slot_time_label(report.dt, report.timezone))
+        .x_y(cell_x(selected), strip.top() + 12.0)
+        .color(BLACK)
+        .font_size(14);
+}
+
+/// The function that scales the temperatures to heights between `bottom` and `top` for the timeline curve.
+/// Equal temperatures are drawn in the middle.
+pub fn temperature_curve(temps: &[f64], bottom: f32, top: f32) -> Vec<f32> {
+    let min = temps.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = temps.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    temps
+        .iter()
+        .map(|temp| {
+            if (max - min).abs() < f64::EPSILON {
+                (bottom + top) / 2.0
+            } else {
+                bottom + ((temp - min) / (max - min)) as f32 * (top - bottom)
+            }
+        })
+        .collect()
+}
+
+/// The function that returns the weekday and local time of a forecast slot, such as "Wed 15:00".
+pub fn slot_time_label(dt: i64, timezone: i64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+    let local = dt + timezone;
+    let day = DAYS[local.div_euclid(86_400).rem_euclid(7) as usize];
+    let seconds = local.rem_euclid(86_400);
+
+    format!("{} {:02}:{:02}", day, seconds / 3600, seconds % 3600 / 60)
+}
+
+/// The function that returns the badge of a report shown from the cache because the API could not be
+/// reached, such as "stale since 14:05" in the city's local time.
+pub fn stale_label(report: &WeatherReport) -> Option<String> {
+    let seconds = (report.stale_since? + report.timezone).rem_euclid(86_400);
+    Some(format!(
+        "stale since {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    ))
+}
+
+/// The function that draws a small icon of the scene layers, used for the forecast timeline.
+fn draw_icon(draw: &Draw, center: Point2, size: f32, layers: &Layers) {
+    if layers.sun {
+        draw.ellipse()
+            .xy(center + vec2(-size * 0.15, size * 0.15))
+            .radius(size * 0.3)
+            .color(YELLOW);
+    }
+    if let Some((_, rain)) = layers.clouds {
+        let cloud_color = if rain { DIMGRAY } else { LIGHTGRAY };
+        draw.ellipse()
+            .xy(center + vec2(0.0, size * 0.1))
+            .w_h(size * 0.8, size * 0.4)
+            .color(cloud_color);
+    }
+    for (i, (color, _)) in layers.haze.iter().take(2).enumerate() {
+        let y = center.y - size * 0.1 - i as f32 * size * 0.15;
+        draw.line()
+            .start(pt2(center.x - size * 0.4, y))
+            .end(pt2(center.x + size * 0.4, y))
+            .weight(1.5)
+            .color(*color);
+    }
+
+    let mut drops = Vec::new();
+    if let Some((color, _)) = layers.rain.first() {
+        drops.push(*color);
+    }
+    if layers.sleet > 0 || layers.snow > 0 {
+        drops.push(WHITE);
+    }
+    for (row, color) in drops.iter().enumerate() {
+        for i in -1..=1 {
+            draw.ellipse()
+                .xy(center + vec2(i as f32 * size * 0.25, -size * (0.2 + row as f32 * 0.15)))
+                .radius(size * 0.06)
+                .color(*color);
+        }
+    }
+
+    if layers.lightning {
+        draw.polyline().weight(1.5).color(YELLOW).points(vec![
+            center + vec2(size * 0.1, 0.0),
+            center + vec2(-size * 0.05, -size * 0.2),
+            center + vec2(size * 0.05, -size * 0.2),
+            center + vec2(-size * 0.1, -size * 0.4),
+        ]);
+    }
+    if layers.squalls || layers.tornado {
+        draw.line()
+            .start(center + vec2(-size * 0.4, -size * 0.3))
+            .end(center + vec2(size * 0.4, -size * 0.25))
+            .weight(1.5)
+            .color(if layers.tornado { DIMGRAY } else { GAINSBORO });
+    }
+    if !layers.unknown.is_empty() {
+        draw.text("?").xy(center).color(DIMGRAY).font_size(12);
+    }
+}
+
+/// The function that returns the color of the temperature.
+/// The function takes in a reference to the temperature as a f64 and returns the color of the temperature as an `Srgb<u8>`.
+/// The color of the temperature is determined by the temperature value.
+pub fn get_temp_color(temperature: &f64) -> Srgb<u8> {
+    let my_temp;
+
+    if temperature > &46.0 {
+        my_temp = BLACK;
+    } else if temperature > &38.0 {
+        my_temp = DARKRED;
+    } else if temperature > &29.0 {
+        my_temp = CRIMSON;
+    } else if temperature > &24.0 {
+        my_temp = ORANGERED;
+    } else if temperature > &16.0 {
+        my_temp = ORANGE;
+    } else if temperature > &10.0 {
+        my_temp = GOLD;
+    } else if temperature > &4.0 {
+        my_temp = LIGHTYELLOW;
+    } else if temperature > &-1.0 {
+        my_temp = PALEGREEN;
+    } else if temperature > &-9.0 {
+        my_temp = POWDERBLUE;
+    } else if temperature > &-18.0 {
+        my_temp = ROYALBLUE;
+    } else if temperature > &-23.0 {
+        my_temp = SLATEBLUE;
+    } else if temperature >= &-29.0 {
+        my_temp = REBECCAPURPLE;
+    } else {
+        my_temp = INDIGO;
+    }
+
+    my_temp
+}
+
+/// The function that draws the weather label along the bottom of the window, placed by the size of the
+/// window so it stays on screen however large the window is.
+/// The forecast lists the descriptions of every reported condition, and the temperature and wind are
+/// written in the units.
+pub fn draw_weather_label(draw: &Draw, win: Rect, report: &WeatherReport, units: Units) {
+    let forecast = format!("Forecast: {}", report.descriptions().join(", "));
+    let forecast_str: &str = &forecast;
+
+    let text_color = if Daylight::from_report(report).darkness() > 0.5 {
+        WHITE
+    } else {
+        BLACK
+    };
+
+    let text_x = win.left() + win.w() * 0.2;
+    let baseline = win.bottom() + (win.h() * 0.1).min(50.0);
+
+    draw.text(forecast_str)
+        .x_y(text_x, baseline + 60.0)
+        .color(text_color)
+        .font_size(24);
+
+    let temp_str = format!(
+        "Temperature: {}",
+        units.format_temperature(report.temperature())
+    );
+
+    draw.text(&temp_str)
+        .x_y(text_x, baseline)
+        .color(text_color)
+        .font_size(24);
+
+    draw_wind_indicator(
+        draw,
+        pt2(win.left() + win.w() * 0.65, baseline + 30.0),
+        &report.wind,
+        units,
+        text_color,
+    );
+
+    if let Some(stale) = stale_label(report) {
+        let badge = pt2(win.right() - 110.0, win.top() - 30.0);
+        draw.rect()
+            .xy(badge)
+            .w_h(190.0, 30.0)
+            .color(rgba(0.7, 0.35, 0.0, 0.85));
+        draw.text(&stale).xy(badge).color(WHITE).font_size(16);
+    }
+}
+
+/// The function that draws a compass with an arrow in the direction the wind blows towards,
+/// with the wind speed, compass point and Beaufort force written next to it.
+fn draw_wind_indicator(draw: &Draw, center: Point2, wind: &Wind, units: Units, color: Srgb<u8>) {
+    let radius = 28.0;
+    draw.ellipse()
+        .xy(center)
+        .radius(radius)
+        .no_fill()
+        .stroke(color)
+        .stroke_weight(2.0);
+    draw.text("N")
+        .xy(center + vec2(0.0, radius + 10.0))
+        .color(color)
+        .font_size(12);
+
+    if wind.speed > 0.0 {
+        let (x, y) = wind::direction(wind);
+        let arrow = vec2(x, y) * radius * 0.8;
+        draw.arrow()
+            .start(center - arrow)
+            .end(center + arrow)
+            .weight(3.0)
+            .color(color);
+    }
+
+    let force = wind::beaufort(wind.speed);
+    let wind_str = format!(
+        "Wind: {:.1} {} {}\nBeaufort {}, {}",
+        units.speed(wind.speed),
+        units.speed_symbol(),
+        wind::compass_point(wind.deg),
+        force,
+        wind::beaufort_name(force)
+    );
+    draw.text(&wind_str)
+        .xy(center + vec2(radius + 130.0, 0.0))
+        .w(240.0)
+        .left_justify()
+        .color(color)
+        .font_size(16);
+}
+
+/// The function that draws the error state when the weather could not be fetched.
+pub fn draw_error(draw: &Draw, win: Rect, texture: &wgpu::Texture, error: &WeatherError) {
+    draw.texture(texture).wh(win.wh());
+    draw.background().color(LIGHTGRAY);
+
+    draw.text("Could not load the weather")
+        .x_y(0.0, win.top() - 200.0)
+        .w(win.w() - 100.0)
+        .color(DARKRED)
+        .font_size(32);
+
+    let message = error.to_string();
+    draw.text(&message)
+        .x_y(0.0, win.top() - 260.0)
+        .w(win.w() - 100.0)
+        .color(BLACK)
+        .font_size(20);
+
+    draw.text("Press 'w' to search for another city.")
+        .x_y(0.0, win.top() - 320.0)
+        .w(win.w() - 100.0)
+        .color(BLACK)
+        .font_size(16);
+}
+
+/// The function that draws a spinner and the text in the bottom right corner while something is loading.
+/// The scene under it keeps playing until the new data arrives.
+pub fn draw_loading(draw: &Draw, win: Rect, text: &str, time: f32) {
+    let center = pt2(win.right() - 40.0, win.bottom() + 40.0);
+    draw.rect()
+        .x_y(win.right() - 170.0, center.y)
+        .w_h(320.0, 50.0)
+        .color(rgba(0.1, 0.1, 0.15, 0.75));
+
+    // Eight dots around a circle, the brightest one turning clockwise
+    let dots = 8;
+    let head = (time * 10.0) as usize % dots;
+    for i in 0..dots {
+        let angle = -(i as f32) / dots as f32 * TAU + PI / 2.0;
+        let age = (head + dots - i) % dots;
+        draw.ellipse()
+            .xy(center + vec2(angle.cos(), angle.sin()) * 12.0)
+            .radius(3.5)
+            .color(rgba(1.0, 1.0, 1.0, 1.0 - age as f32 / dots as f32));
+    }
+
+    draw.text(text)
+        .x_y(win.right() - 190.0, center.y)
+        .w_h(260.0, 50.0)
+        .right_justify()
+        .color(WHITE)
+        .font_size(16);
+}
+
+/// The function that makes the drawing of the old scene see-through during a fade to a new scene, with
+/// `progress` from 0 to 1. Only the alpha of the drawing is lowered, so the old scene laid over the new one
+/// blends into it without darkening.
+pub fn draw_fade(draw: &Draw, win: Rect, progress: f32) {
+    let keep = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let fade_out = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    };
+    draw.color_blend(keep)
+        .alpha_blend(fade_out)
+        .rect()
+        .wh(win.wh())
+        .color(rgba(0.0, 0.0, 0.0, progress.clamp(0.0, 1.0)));
+}
+
+/// The function that draws the lightning of a thunderstorm.
+fn draw_lightning(draw: &Draw, win: Rect, rng: &mut StdRng) {
+    for _ in 0..10 {
+        let start_x = rng.gen_range(win.left()..win.right());
+        let start_y = rng.gen_range(win.bottom()..win.top());
+        let end_x = rng.gen_range(win.left()..win.right());
+        let end_y = rng.gen_range(win.bottom()..win.top());
+        draw.polyline()
+            .weight(2.0)
+            .points(vec![pt2(start_x, start_y), pt2(end_x, end_y)])
+            .color(YELLOW);
+    }
+}
+
+/// The function that draws the wind lines of squalls, pointing the way the wind blows.
+/// The north and south part of the direction is flattened, since the scene looks north over the city.
+fn draw_squalls(draw: &Draw, win: Rect, wind: &Wind, rng: &mut StdRng) {
+    let (x, y) = wind::direction(wind);
+    let line = vec2(x, y * 0.3).normalize_or_zero();
+    let line = if line == Vec2::ZERO {
+        vec2(1.0, 0.0)
+    } else {
+        line
+    };
+
+    let n_lines = 50;
+    for _ in 0..n_lines {
+        let start_x = rng.gen_range(win.left()..win.right());
+        let start_y = rng.gen_range(win.bottom()..win.top());
+        let length = rng.gen_range(50.0..150.0);
+        let end_x = start_x + line.x * length;
+        let end_y = start_y + line.y * length + rng.gen_range(-20.0..20.0);
+        draw.line()
+            .start(pt2(start_x, start_y))
+            .end(pt2(end_x, end_y))
+            .weight(2.0)
+            .color(GAINSBORO);
+    }
+}
+
+/// The function that draws the funnel of a tornado.
+fn draw_tornado(draw: &Draw, win: Rect) {
+    let funnel_height = 300.0;
+    let funnel_width = 200.0;
+    let funnel_steps = 50;
+    let step_height = funnel_height / funnel_steps as f32;
+    let step_width = funnel_width / funnel_steps as f32;
+
+    for i in 0..funnel_steps {
+        let y = (win.top() - 100.0) - i as f32 * step_height;
+        let width = funnel_width - i as f32 * step_width;
+        draw.ellipse()
+            .x_y(0.0, y)
+            .w_h(width, step_height)
+            .color(DIMGRAY)
+            .stroke(BLACK)
+            .stroke_weight(1.0);
+    }
+}
+
+/// The function that draws a note for weather codes without a visualization.
+fn draw_unknown(draw: &Draw, win: Rect, id: i64) {
+    let message = format!("No visualization for weather code {}", id);
+    draw.text(&message)
+        .x_y(0.0, win.top() - 100.0)
+        .w(win.w() - 100.0)
+        .color(DIMGRAY)
+        .font_size(20);
+}
+
+/// The function that returns where the sun is by day, or the moon by night, on its arc across the sky.
+/// The arc runs from the left of the window at sunrise to the right at sunset, following the direction of
+/// the sun when the report has coordinates, and its height follows the elevation of the sun.
+pub fn sky_position(win: Rect, daylight: &Daylight) -> Point2 {
+    let horizon = win.bottom() + win.h() * 0.4;
+    let highest = win.top() - 100.0;
+    let x = win.left() + 100.0 + daylight.across * (win.w() - 200.0);
+    let height = (daylight.elevation.abs() / MAX_ELEVATION).min(1.0);
+    pt2(x, horizon + height * (highest - horizon))
+}
+
+/// The function that darkens the city at night and draws the glow of twilight around sunrise and sunset.
+fn draw_sky(draw: &Draw, win: Rect, daylight: &Daylight) {
+    let darkness = daylight.darkness();
+    if darkness > 0.0 {
+        draw.rect()
+            .xy(win.xy())
+            .wh(win.wh())
+            .color(rgba(0.02, 0.03, 0.15, 0.65 * darkness));
+    }
+
+    let twilight = daylight.twilight();
+    if twilight > 0.0 {
+        let (red, green, blue) = match daylight.phase {
+            Phase::Dawn => (1.0, 0.65, 0.4),
+            _ => (0.95, 0.45, 0.3),
+        };
+        let n_bands = 16;
+        let band_height = win.h() * 0.6 / n_bands as f32;
+        for i in 0..n_bands {
+            let y = win.bottom() + win.h() * 0.2 + (i as f32 + 0.5) * band_height;
+            let alpha = 0.5 * twilight * (1.0 - i as f32 / n_bands as f32);
+            draw.rect()
+                .x_y(win.x(), y)
+                .w_h(win.w(), band_height)
+                .color(rgba(red, green, blue, alpha));
+        }
+    }
+}
+
+/// The function that draws the stars of the night sky. The stars keep their places and twinkle, and
+/// fade in as the sky gets dark.
+fn draw_stars(draw: &Draw, win: Rect, daylight: &Daylight, time: f32) {
+    let brightness = ((daylight.darkness() - 0.5) * 2.0).clamp(0.0, 1.0);
+    if brightness <= 0.0 {
+        return;
+    }
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for i in 0..120 {
+        let x = rng.gen_range(win.left()..win.right());
+        let y = rng.gen_range(win.y() - win.h() * 0.1..win.top());
+        let twinkle = 0.7 + 0.3 * (time * 3.0 + i as f32).sin();
+        draw.ellipse()
+            .x_y(x, y)
+            .radius(rng.gen_range(0.8..1.8) * twinkle)
+            .color(rgba(1.0, 1.0, 0.95, brightness * twinkle));
+    }
+}
+
+/// The function that draws the moon in its phase, with the dark part faintly visible.
+fn draw_moon(draw: &Draw, position: Point2, moon: &MoonPhase) {
+    let radius = 30.0;
+    draw.ellipse()
+        .xy(position)
+        .radius(radius)
+        .color(rgba(0.95, 0.95, 0.85, 0.15));
+
+    let outline = moon_outline(moon.phase as f32, radius);
+    if !outline.is_empty() {
+        draw.polygon()
+            .points(outline.into_iter().map(|point| position + point))
+            .color(rgb(0.95, 0.95, 0.85));
+    }
+}
+
+/// The function that returns the outline of the lit part of the moon around its center.
+/// The outline follows the lit edge of the moon and comes back along the terminator, an ellipse that
+/// narrows to a line at the quarters. A waxing moon is lit on the right.
+pub fn moon_outline(phase: f32, radius: f32) -> Vec<Point2> {
+    let side = if phase < 0.5 { 1.0 } else { -1.0 };
+    let terminator = (phase * TAU).cos();
+    if (1.0 - terminator).abs() < 1e-3 {
+        return Vec::new();
+    }
+
+    let n_points = 24;
+    let limb = (0..=n_points).map(|i| {
+        let angle = -PI / 2.0 + PI * i as f32 / n_points as f32;
+        pt2(side * radius * angle.cos(), radius * angle.sin())
+    });
+    let inner = (0..=n_points).rev().map(|i| {
+        let angle = -PI / 2.0 + PI * i as f32 / n_points as f32;
+        pt2(
+            side * radius * angle.cos() * terminator,
+            radius * angle.sin(),
+        )
+    });
+
+    limb.chain(inner).collect()
+}
+
+/// The function that draws the sun at the given position.
+fn draw_clear_sky(draw: &Draw, position: Point2) {
+    // Draw the sun
+    let sun_radius = 50.0;
+    draw.ellipse()
+        .xy(position)
+        .w_h(sun_radius * 2.0, sun_radius * 2.0)
+        .color(YELLOW);
+
+    // Draw sun rays
+    let n_rays = 20;
+    let ray_length = 100.0;
+    for i in 0..n_rays {
+        let angle = i as f32 * (360.0 / n_rays as f32);
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let direction = vec2(cos, sin);
+        draw.line()
+            .start(position + direction * sun_radius)
+            .end(position + direction * (sun_radius + ray_length))
+            .weight(2.0)
+            .color(YELLOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::condition::Intensity;
+
+    #[test]
+    fn test_get_temp_color() {
+        assert_eq!(get_temp_color(&50.0), BLACK);
+        assert_eq!(get_temp_color(&40.0), DARKRED);
+        assert_eq!(get_temp_color(&30.0), CRIMSON);
+        assert_eq!(get_temp_color(&25.0), ORANGERED);
+        assert_eq!(get_temp_color(&20.0), ORANGE);
+        assert_eq!(get_temp_color(&15.0), GOLD);
+        assert_eq!(get_temp_color(&5.0), LIGHTYELLOW);
+        assert_eq!(get_temp_color(&0.0), PALEGREEN);
+        assert_eq!(get_temp_color(&-5.0), POWDERBLUE);
+        assert_eq!(get_temp_color(&-15.0), ROYALBLUE);
+        assert_eq!(get_temp_color(&-20.0), SLATEBLUE);
+        assert_eq!(get_temp_color(&-25.0), REBECCAPURPLE);
+        assert_eq!(get_temp_color(&-30.0), INDIGO);
+    }
+
+    #[test]
+    fn test_rain_over_mist() {
+        let layers = Layers::from_conditions(&[Condition::Rain(Intensity::Light), Condition::Mist]);
+
+        assert_eq!(layers.clouds, Some((25, true)));
+        assert_eq!(layers.rain, vec![(BLUE, 25)]);
+        assert_eq!(layers.haze, vec![(LIGHTGRAY, 2000)]);
+        assert!(!layers.sun);
+    }
+
+    #[test]
+    fn test_snow_with_fog() {
+        let layers = Layers::from_conditions(&[Condition::Snow(Intensity::Heavy), Condition::Fog]);
+
+        assert_eq!(layers.snow, 300);
+        assert_eq!(layers.haze, vec![(WHITESMOKE, 4000)]);
+        assert_eq!(layers.clouds, None);
+    }
+
+    #[test]
+    fn test_densest_clouds_win() {
+        let layers = Layers::from_conditions(&[
+            Condition::Clouds(CloudCover::Few),
+            Condition::Thunderstorm(Intensity::Heavy),
+        ]);
+
+        assert_eq!(layers.clouds, Some((100, true)));
+        assert!(layers.lightning);
+        assert!(!layers.sun);
+    }
+
+    #[test]
+    fn test_sun_through_few_clouds() {
+        assert!(Layers::from_conditions(&[Condition::Clear]).sun);
+        assert!(Layers::from_conditions(&[Condition::Clouds(CloudCover::Few)]).sun);
+        assert!(!Layers::from_conditions(&[Condition::Clouds(CloudCover::Broken)]).sun);
+    }
+
+    #[test]
+    fn test_slot_time_label() {
+        // 2023-11-15 00:00:00 UTC was a Wednesday
+        assert_eq!(slot_time_label(1700006400, 0), "Wed 00:00");
+        assert_eq!(slot_time_label(1700006400, 9 * 3600), "Wed 09:00");
+        assert_eq!(slot_time_label(1700006400, -5 * 3600), "Tue 19:00");
+    }
+
+    #[test]
+    fn test_clouds_follow_the_seed() {
+        let report: WeatherReport = serde_json::from_str(
+            r#"{"weather": [{"id": 803}], "main": {"temp": 9.5}, "clouds": {"all": 60}, "dt": 1700006400}"#,
+        )
+        .unwrap();
+        let win = Rect::from_w_h(1024.0, 512.0);
+        let puffs = |seed| {
+            SceneState::new(&report, win, &mut StdRng::seed_from_u64(seed))
+                .clouds
+                .unwrap()
+                .puffs(win, 0.0, 0.0)
+        };
+
+        assert_eq!(puffs(1), puffs(1));
+        assert_ne!(puffs(1), puffs(2));
+    }
+
+    #[test]
+    fn test_seeded_scenes() {
+        let report: WeatherReport = serde_json::from_str(
+            r#"{"weather": [{"id": 211}], "main": {"temp": 9.5}, "clouds": {"all": 90}, "dt": 1700006400}"#,
+        )
+        .unwrap();
+        let win = Rect::from_w_h(1024.0, 512.0);
+        let (state, mut rng) = SceneState::seeded(&report, win, 7);
+        let (same, mut same_rng) = SceneState::seeded(&report, win, 7);
+
+        let puffs = |state: &SceneState| state.clouds.as_ref().unwrap().puffs(win, 0.0, 0.0);
+        assert_eq!(puffs(&state), puffs(&same));
+        assert_eq!(frame_seed(&mut rng), frame_seed(&mut same_rng));
+    }
+
+    #[test]
+    fn test_stale_label() {
+        let mut report: WeatherReport =
+            serde_json::from_str(r#"{"main": {"temp": 9.5}, "timezone": 3600}"#).unwrap();
+        assert_eq!(stale_label(&report), None);
+
+        report.stale_since = Some(1700006400 + 14 * 3600 + 5 * 60);
+        assert_eq!(stale_label(&report).as_deref(), Some("stale since 15:05"));
+    }
+
+    #[test]
+    fn test_temperature_curve() {
+        assert_eq!(
+            temperature_curve(&[0.0, 5.0, 10.0], 0.0, 20.0),
+            vec![0.0, 10.0, 20.0]
+        );
+        assert_eq!(temperature_curve(&[3.0, 3.0], 0.0, 20.0), vec![10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_cloud_percent() {
+        let mut report: WeatherReport =
+            serde_json::from_str(r#"{"main": {"temp": 10.0}}"#).unwrap();
+        let rain = Layers::from_conditions(&[Condition::Rain(Intensity::Light)]);
+
+        assert_eq!(cloud_percent(&report, &rain), (25, true));
+
+        report.clouds.all = 37;
+        assert_eq!(cloud_percent(&report, &rain), (37, true));
+        assert_eq!(cloud_percent(&report, &Layers::default()), (37, false));
+    }
+
+    #[test]
+    fn test_moon_outline() {
+        assert!(moon_outline(0.0, 30.0).is_empty());
+
+        // The first quarter is lit on the right up to a straight terminator
+        let first_quarter = moon_outline(0.25, 30.0);
+        assert!(first_quarter.iter().all(|point| point.x > -1e-3));
+        assert!(first_quarter
+            .iter()
+            .any(|point| (point.x - 30.0).abs() < 1e-3));
+
+        // The full moon reaches both edges and the last quarter is lit on the left
+        let full = moon_outline(0.5, 30.0);
+        assert!(full.iter().any(|point| (point.x + 30.0).abs() < 1e-3));
+        assert!(moon_outline(0.75, 30.0).iter().all(|point| point.x < 1e-3));
+    }
+
+    #[test]
+    fn test_scene_seed() {
+        let mut report: WeatherReport =
+            serde_json::from_str(r#"{"name": "London", "dt": 1700000000, "main": {"temp": 9.0}}"#)
+                .unwrap();
+        let seed = scene_seed(&report);
+        assert_eq!(seed, scene_seed(&report.clone()));
+
+        report.dt += 600;
+        assert_ne!(scene_seed(&report), seed);
+        report.dt -= 600;
+        report.name = "Madrid".to_string();
+        assert_ne!(scene_seed(&report), seed);
+    }
+
+    #[test]
+    fn test_unknown_codes_are_kept() {
+        let layers = Layers::from_conditions(&[Condition::Unknown(999), Condition::Squall]);
+
+        assert_eq!(layers.unknown, vec![999]);
+        assert!(layers.squalls);
+        assert!(!layers.tornado);
+    }
+}
@@ -0,0 +1,330 @@
+//! The city search box drawn over the window.
+//!
+//! Pressing 'w' opens a text field at the top of the window. Once the user stops typing for a moment, the query
+//! is looked up with the provider's geocoding and the matches are listed under the field, after the
+//! recently shown places that match. The arrow keys pick a suggestion and Enter loads it.
+
+use crate::provider::{Location, Place};
+use nannou::prelude::*;
+
+/// The seconds the query has to stay unchanged before it is looked up.
+pub const LOOKUP_DELAY: f32 = 0.4;
+
+/// The shortest query that is looked up.
+const MIN_LOOKUP_LEN: usize = 2;
+
+/// The number of recently shown places that are remembered.
+const HISTORY_LEN: usize = 8;
+
+/// The most suggestions listed under the text field.
+const MAX_SUGGESTIONS: usize = 6;
+
+/// A place the user can choose in the search box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The text listed for the place, such as "Paris, Texas, US".
+    pub label: String,
+    /// The name of the city, used to choose the city image.
+    pub name: String,
+    pub location: Location,
+}
+
+impl Suggestion {
+    /// The function that returns the suggestion for a place found by the geocoding.
+    pub fn from_place(place: &Place) -> Suggestion {
+        Suggestion {
+            label: place.label(),
+            name: place.name.clone(),
+            location: place.location(),
+        }
+    }
+
+    /// The function that returns the suggestion for a city by name, for providers without geocoding.
+    pub fn from_name(name: &str) -> Suggestion {
+        Suggestion {
+            label: name.to_string(),
+            name: name.to_string(),
+            location: Location::City(name.to_string()),
+        }
+    }
+}
+
+/// The state of the search box.
+#[derive(Debug, Clone, Default)]
+pub struct SearchBox {
+    pub open: bool,
+    pub query: String,
+    pub suggestions: Vec<Suggestion>,
+    /// The index of the highlighted suggestion.
+    pub selected: usize,
+    /// The places shown most recently, the latest first.
+    history: Vec<Suggestion>,
+    /// The time the query last changed, or `None` once it has been looked up.
+    changed_at: Option<f32>,
+}
+
+impl SearchBox {
+    /// The function that opens the search box with an empty query, suggesting the recent places.
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.changed_at = None;
+        self.suggestions = self.history.clone();
+        self.selected = 0;
+    }
+
+    /// The function that closes the search box.
+    pub fn close(&mut self) {
+        self.open = false;
+        self.changed_at = None;
+    }
+
+    /// The function that adds a typed character to the query at `time` seconds.
+    pub fn type_char(&mut self, c: char, time: f32) {
+        if c.is_control() {
+            return;
+        }
+        self.query.push(c);
+        self.query_changed(time);
+    }
+
+    /// The function that removes the last character of the query at `time` seconds.
+    pub fn backspace(&mut self, time: f32) {
+        if self.query.pop().is_some() {
+            self.query_changed(time);
+        }
+    }
+
+    /// The function that filters the suggestions to the recent places matching the new query, until the
+    /// geocoding results arrive.
+    fn query_changed(&mut self, time: f32) {
+        self.changed_at = Some(time);
+        self.suggestions = self.history_matches();
+        self.selected = 0;
+    }
+
+    /// The function that moves the highlighted suggestion up or down, wrapping around the list.
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.suggestions.len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /// The function that returns the query to look up once the user stopped typing at `time` seconds.
+    /// Every query is only returned once.
+    pub fn take_lookup(&mut self, time: f32) -> Option<String> {
+        let changed_at = self.changed_at?;
+        let query = self.query.trim();
+        if !self.open || time - changed_at < LOOKUP_DELAY || query.chars().count() < MIN_LOOKUP_LEN
+        {
+            return None;
+        }
+        self.changed_at = None;
+        Some(query.to_string())
+    }
+
+    /// The function that lists the places found for the query after the recent places matching it.
+    /// Results for an older query are ignored.
+    pub fn set_places(&mut self, query: &str, places: &[Place]) {
+        if query != self.query.trim() {
+            return;
+        }
+
+        let mut suggestions = self.history_matches();
+        for place in places {
+            let suggestion = Suggestion::from_place(place);
+            if !suggestions.iter().any(|s| s.label == suggestion.label) {
+                suggestions.push(suggestion);
+            }
+        }
+        suggestions.truncate(MAX_SUGGESTIONS);
+        self.suggestions = suggestions;
+        self.selected = self.selected.min(self.suggestions.len().saturating_sub(1));
+    }
+
+    /// The function that returns the recent places whose label starts with the query, ignoring case.
+    fn history_matches(&self) -> Vec<Suggestion> {
+        let query = self.query.trim().to_lowercase();
+        self.history
+            .iter()
+            .filter(|s| s.label.to_lowercase().starts_with(&query))
+            .cloned()
+            .collect()
+    }
+
+    /// The function that returns the place to load when Enter is pressed: the highlighted suggestion,
+    /// or the typed city when there are no suggestions.
+    pub fn choice(&self) -> Option<Suggestion> {
+        match self.suggestions.get(self.selected) {
+            Some(suggestion) => Some(suggestion.clone()),
+            None if !self.query.trim().is_empty() => Some(Suggestion::from_name(self.query.trim())),
+            None => None,
+        }
+    }
+
+    /// The function that remembers a place that was shown, as the most recent one.
+    pub fn remember(&mut self, suggestion: Suggestion) {
+        self.history.retain(|s| s.label != suggestion.label);
+        self.history.insert(0, suggestion);
+        self.history.truncate(HISTORY_LEN);
+    }
+
+    /// The function that draws the text field and the suggestions at the top of the window.
+    pub fn draw(&self, draw: &Draw, win: Rect, time: f32) {
+        if !self.open {
+            return;
+        }
+
+        let width = 520.0;
+        let row_height = 32.0;
+        let field = Rect::from_x_y_w_h(0.0, win.top() - 50.0, width, 40.0);
+        draw.rect()
+            .xy(field.xy())
+            .wh(field.wh())
+            .color(rgba(0.1, 0.1, 0.15, 0.9))
+            .stroke(WHITE)
+            .stroke_weight(2.0);
+
+        // A blinking cursor after the query
+        let cursor = if (time * 2.0) as i32 % 2 == 0 {
+            "|"
+        } else {
+            ""
+        };
+        let text = if self.query.is_empty() {
+            format!("Search for a city...{}", cursor)
+        } else {
+            format!("{}{}", self.query, cursor)
+        };
+        draw.text(&text)
+            .xy(field.xy())
+            .wh(field.pad_left(12.0).wh())
+            .left_justify()
+            .color(if self.query.is_empty() { GRAY } else { WHITE })
+            .font_size(18);
+
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let y = field.bottom() - row_height * (i as f32 + 0.5);
+            let selected = i == self.selected;
+            draw.rect()
+                .x_y(0.0, y)
+                .w_h(width, row_height)
+                .color(if selected {
+                    rgba(0.25, 0.4, 0.7, 0.95)
+                } else {
+                    rgba(0.1, 0.1, 0.15, 0.85)
+                });
+            draw.text(&suggestion.label)
+                .x_y(0.0, y)
+                .w_h(width - 24.0, row_height)
+                .left_justify()
+                .color(WHITE)
+                .font_size(16);
+        }
+
+        draw.text("Enter to load, arrows to choose, Esc to cancel")
+            .x_y(
+                0.0,
+                field.bottom() - row_height * (self.suggestions.len() as f32 + 0.5),
+            )
+            .w(width)
+            .color(LIGHTGRAY)
+            .font_size(12);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(name: &str, state: &str, country: &str) -> Place {
+        Place {
+            name: name.to_string(),
+            lat: 1.0,
+            lon: 2.0,
+            country: country.to_string(),
+            state: Some(state.to_string()),
+        }
+    }
+
+    fn type_query(search: &mut SearchBox, query: &str, time: f32) {
+        for c in query.chars() {
+            search.type_char(c, time);
+        }
+    }
+
+    #[test]
+    fn test_lookup_waits_for_typing_to_stop() {
+        let mut search = SearchBox::default();
+        search.open();
+        type_query(&mut search, "Par", 1.0);
+
+        assert_eq!(search.take_lookup(1.1), None);
+        assert_eq!(search.take_lookup(1.5), Some("Par".to_string()));
+        assert_eq!(search.take_lookup(2.0), None);
+
+        search.backspace(3.0);
+        search.backspace(3.0);
+        assert_eq!(search.query, "P");
+        assert_eq!(search.take_lookup(4.0), None);
+    }
+
+    #[test]
+    fn test_suggestions_and_choice() {
+        let mut search = SearchBox::default();
+        search.remember(Suggestion::from_place(&place("Paris", "Texas", "US")));
+        search.remember(Suggestion::from_name("London"));
+        search.open();
+        assert_eq!(search.suggestions.len(), 2);
+        assert_eq!(search.suggestions[0].label, "London");
+
+        type_query(&mut search, "Pa", 0.0);
+        search.set_places(
+            "Pa",
+            &[
+                place("Paris", "Île-de-France", "FR"),
+                place("Paris", "Texas", "US"),
+            ],
+        );
+        let labels: Vec<_> = search
+            .suggestions
+            .iter()
+            .map(|s| s.label.as_str())
+            .collect();
+        assert_eq!(labels, ["Paris, Texas, US", "Paris, Île-de-France, FR"]);
+
+        search.move_selection(1);
+        assert_eq!(search.choice().unwrap().label, "Paris, Île-de-France, FR");
+        search.move_selection(1);
+        assert_eq!(search.choice().unwrap().label, "Paris, Texas, US");
+
+        // Results for an older query are ignored
+        search.set_places("P", &[place("Porto", "Porto", "PT")]);
+        assert_eq!(search.suggestions.len(), 2);
+    }
+
+    #[test]
+    fn test_typed_city_without_suggestions() {
+        let mut search = SearchBox::default();
+        search.open();
+        assert_eq!(search.choice(), None);
+
+        type_query(&mut search, "KBNA\n", 0.0);
+        assert_eq!(search.choice(), Some(Suggestion::from_name("KBNA")));
+    }
+
+    #[test]
+    fn test_history_is_recent_first_without_duplicates() {
+        let mut search = SearchBox::default();
+        for i in 0..10 {
+            search.remember(Suggestion::from_name(&format!("City {}", i)));
+        }
+        search.remember(Suggestion::from_name("City 5"));
+        search.open();
+
+        assert_eq!(search.suggestions.len(), HISTORY_LEN);
+        assert_eq!(search.suggestions[0].label, "City 5");
+        assert_eq!(search.suggestions[1].label, "City 9");
+    }
+}
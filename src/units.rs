@@ -0,0 +1,94 @@
+//! The units temperatures and wind speeds are shown in.
+//!
+//! Every provider turns its data into degrees Celsius and meters per second, so reports and scenes always
+//! work in those. The units only change the text shown to the user.
+
+/// A system of units, named like the `units` parameter of OpenWeatherMap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    /// Degrees Celsius and meters per second.
+    #[default]
+    Metric,
+    /// Degrees Fahrenheit and miles per hour.
+    Imperial,
+    /// Kelvin and meters per second.
+    Standard,
+}
+
+impl Units {
+    /// The function that returns the units with the given name, such as "metric" or "fahrenheit".
+    /// The function returns `None` if there are no units with that name.
+    pub fn from_name(name: &str) -> Option<Units> {
+        match name.trim().to_lowercase().as_str() {
+            "metric" | "celsius" | "c" => Some(Units::Metric),
+            "imperial" | "fahrenheit" | "f" => Some(Units::Imperial),
+            "standard" | "kelvin" | "k" => Some(Units::Standard),
+            _ => None,
+        }
+    }
+
+    /// The function that converts a temperature in degrees Celsius to these units.
+    pub fn temperature(self, celsius: f64) -> f64 {
+        match self {
+            Units::Metric => celsius,
+            Units::Imperial => celsius * 9.0 / 5.0 + 32.0,
+            Units::Standard => celsius + 273.15,
+        }
+    }
+
+    /// The function that returns the symbol of the temperature unit, such as "°C".
+    pub fn temperature_symbol(self) -> &'static str {
+        match self {
+            Units::Metric => "°C",
+            Units::Imperial => "°F",
+            Units::Standard => "K",
+        }
+    }
+
+    /// The function that converts a wind speed in meters per second to these units.
+    pub fn speed(self, meters_per_second: f64) -> f64 {
+        match self {
+            Units::Imperial => meters_per_second * 2.236936,
+            Units::Metric | Units::Standard => meters_per_second,
+        }
+    }
+
+    /// The function that returns the symbol of the speed unit, such as "m/s".
+    pub fn speed_symbol(self) -> &'static str {
+        match self {
+            Units::Imperial => "mph",
+            Units::Metric | Units::Standard => "m/s",
+        }
+    }
+
+    /// The function that writes a temperature in degrees Celsius in these units, such as "54.5 °F".
+    pub fn format_temperature(self, celsius: f64) -> String {
+        format!(
+            "{:.1} {}",
+            self.temperature(celsius),
+            self.temperature_symbol()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Units::from_name("metric"), Some(Units::Metric));
+        assert_eq!(Units::from_name(" Imperial "), Some(Units::Imperial));
+        assert_eq!(Units::from_name("kelvin"), Some(Units::Standard));
+        assert_eq!(Units::from_name("furlongs"), None);
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Units::Metric.format_temperature(12.5), "12.5 °C");
+        assert_eq!(Units::Imperial.format_temperature(12.5), "54.5 °F");
+        assert_eq!(Units::Standard.format_temperature(-273.15), "0.0 K");
+        assert!((Units::Imperial.speed(10.0) - 22.37).abs() < 0.01);
+        assert_eq!(Units::Standard.speed(10.0), 10.0);
+    }
+}
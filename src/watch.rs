@@ -0,0 +1,124 @@
+//! The watch mode for always-on displays.
+//!
+//! In watch mode the window fetches the weather of the shown city again on an interval. When the weather
+//! cannot be fetched, the next attempt waits twice as long as the last one, up to a maximum, so a lost
+//! connection or an exhausted API quota is not hammered with requests.
+
+/// The settings of the watch mode, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Watch {
+    /// The time between two refreshes.
+    pub interval: f32,
+    /// The time the old scene takes to fade into the new one when the weather changes.
+    pub fade: f32,
+    /// The longest time between two attempts after failed refreshes.
+    pub max_backoff: f32,
+}
+
+/// The default fade between two scenes.
+pub const DEFAULT_FADE: f32 = 2.0;
+
+/// The default longest time between two attempts after failed refreshes, an hour.
+pub const DEFAULT_MAX_BACKOFF: f32 = 3600.0;
+
+impl Watch {
+    /// The function that returns the watch mode refreshing every `interval` seconds with the default fade
+    /// and backoff.
+    pub fn every(interval: f32) -> Watch {
+        Watch {
+            interval,
+            fade: DEFAULT_FADE,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+/// The schedule of the refreshes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refresher {
+    pub watch: Watch,
+    /// The time of the next refresh.
+    next_at: f32,
+    /// The number of refreshes that failed in a row.
+    failures: u32,
+}
+
+impl Refresher {
+    /// The function that returns the schedule with the first refresh an interval after `now`.
+    pub fn new(watch: Watch, now: f32) -> Refresher {
+        Refresher {
+            watch,
+            next_at: now + watch.interval,
+            failures: 0,
+        }
+    }
+
+    /// The function that returns whether the weather should be fetched again at `now`.
+    pub fn is_due(&self, now: f32) -> bool {
+        now >= self.next_at
+    }
+
+    /// The function that schedules the next refresh after the weather was fetched at `now`.
+    pub fn succeeded(&mut self, now: f32) {
+        self.failures = 0;
+        self.next_at = now + self.watch.interval;
+    }
+
+    /// The function that schedules the next attempt after the weather could not be fetched at `now`.
+    pub fn failed(&mut self, now: f32) {
+        self.failures += 1;
+        self.next_at = now + self.delay();
+    }
+
+    /// The function that returns the time until the next attempt: the interval, doubled for every
+    /// failed refresh in a row, up to the maximum backoff.
+    pub fn delay(&self) -> f32 {
+        let backoff = self.watch.interval * 2.0f32.powi(self.failures.min(30) as i32);
+        backoff.min(self.watch.max_backoff.max(self.watch.interval))
+    }
+
+    /// The function that returns the number of refreshes that failed in a row.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refreshes_on_the_interval() {
+        let mut refresher = Refresher::new(Watch::every(60.0), 5.0);
+        assert!(!refresher.is_due(64.0));
+        assert!(refresher.is_due(65.0));
+
+        refresher.succeeded(65.0);
+        assert!(!refresher.is_due(124.0));
+        assert!(refresher.is_due(125.0));
+    }
+
+    #[test]
+    fn test_backs_off_on_failures() {
+        let watch = Watch {
+            interval: 60.0,
+            fade: 1.0,
+            max_backoff: 300.0,
+        };
+        let mut refresher = Refresher::new(watch, 0.0);
+
+        let delays: Vec<f32> = (0..5)
+            .map(|_| {
+                refresher.failed(0.0);
+                refresher.delay()
+            })
+            .collect();
+        assert_eq!(delays, [120.0, 240.0, 300.0, 300.0, 300.0]);
+        assert_eq!(refresher.failures(), 5);
+        assert!(!refresher.is_due(299.0));
+
+        refresher.succeeded(300.0);
+        assert_eq!(refresher.delay(), 60.0);
+        assert!(refresher.is_due(360.0));
+    }
+}
@@ -0,0 +1,118 @@
+//! Helpers to turn the reported wind into movement on screen and into words for the label.
+
+use crate::report::Wind;
+
+/// How many pixels per second the scene moves for every meter per second of wind.
+pub const PIXELS_PER_METER: f32 = 20.0;
+
+/// The names of the Beaufort scale forces, from 0 to 12.
+const BEAUFORT_NAMES: [&str; 13] = [
+    "calm",
+    "light air",
+    "light breeze",
+    "gentle breeze",
+    "moderate breeze",
+    "fresh breeze",
+    "strong breeze",
+    "near gale",
+    "gale",
+    "strong gale",
+    "storm",
+    "violent storm",
+    "hurricane",
+];
+
+/// The upper wind speed of every Beaufort force from 0 to 11 in meters per second.
+const BEAUFORT_LIMITS: [f64; 12] = [
+    0.5, 1.5, 3.3, 5.5, 7.9, 10.7, 13.8, 17.1, 20.7, 24.4, 28.4, 32.6,
+];
+
+/// The function that returns the direction the wind blows towards as a unit vector, with x pointing east
+/// and y pointing north. The reported direction is where the wind blows from, as usual in meteorology.
+pub fn direction(wind: &Wind) -> (f32, f32) {
+    let (sin, cos) = (wind.deg as f32).to_radians().sin_cos();
+    (-sin, -cos)
+}
+
+/// The function that returns how fast the scene drifts sideways in pixels per second.
+/// The scene looks north, so a wind blowing towards the east moves things to the right.
+pub fn drift(wind: &Wind) -> f32 {
+    direction(wind).0 * wind.speed as f32 * PIXELS_PER_METER
+}
+
+/// The function that returns the Beaufort force for the wind speed in meters per second.
+pub fn beaufort(speed: f64) -> u8 {
+    BEAUFORT_LIMITS
+        .iter()
+        .position(|limit| speed < *limit)
+        .unwrap_or(BEAUFORT_LIMITS.len()) as u8
+}
+
+/// The function that returns the name of the Beaufort force, such as "moderate breeze".
+pub fn beaufort_name(force: u8) -> &'static str {
+    BEAUFORT_NAMES[(force as usize).min(BEAUFORT_NAMES.len() - 1)]
+}
+
+/// The 16 compass points clockwise from north, 22.5 degrees apart.
+const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+/// The function that returns the 16-point compass name of the direction in degrees, such as "SW".
+pub fn compass_point(deg: f64) -> &'static str {
+    let index = (deg.rem_euclid(360.0) / 22.5).round() as usize % COMPASS_POINTS.len();
+    COMPASS_POINTS[index]
+}
+
+/// The function that converts a compass point such as "SSW" into degrees.
+pub fn compass_degrees(point: &str) -> Option<f64> {
+    COMPASS_POINTS
+        .iter()
+        .position(|p| *p == point)
+        .map(|i| i as f64 * 22.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wind(speed: f64, deg: f64) -> Wind {
+        Wind {
+            speed,
+            deg,
+            gust: None,
+        }
+    }
+
+    #[test]
+    fn test_drift() {
+        // A west wind blows towards the east, to the right of the screen
+        assert!((drift(&wind(5.0, 270.0)) - 100.0).abs() < 1e-3);
+        assert!((drift(&wind(5.0, 90.0)) + 100.0).abs() < 1e-3);
+        assert!(drift(&wind(5.0, 0.0)).abs() < 1e-3);
+        assert_eq!(drift(&wind(0.0, 270.0)), 0.0);
+    }
+
+    #[test]
+    fn test_beaufort() {
+        assert_eq!(beaufort(0.0), 0);
+        assert_eq!(beaufort(1.0), 1);
+        assert_eq!(beaufort(5.66), 4);
+        assert_eq!(beaufort(18.0), 8);
+        assert_eq!(beaufort(40.0), 12);
+        assert_eq!(beaufort_name(4), "moderate breeze");
+        assert_eq!(beaufort_name(12), "hurricane");
+    }
+
+    #[test]
+    fn test_compass_point() {
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(230.0), "SW");
+        assert_eq!(compass_point(350.0), "N");
+        assert_eq!(compass_point(-90.0), "W");
+        assert_eq!(compass_point(100.0), "E");
+        assert_eq!(compass_degrees("SSW"), Some(202.5));
+        assert_eq!(compass_degrees("?"), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! A background thread for the network requests of the window.
+//!
+//! The worker owns the weather provider and runs jobs with it one after the other, so fetching the weather
+//! never blocks drawing. The results are posted back over a channel and picked up by the window's update
+//! function with `try_recv`.
+
+use crate::provider::WeatherProvider;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// A job the worker runs with the provider.
+type Job<T> = Box<dyn FnOnce(&dyn WeatherProvider) -> T + Send>;
+
+/// A background thread that runs jobs with the weather provider and sends back their results.
+pub struct Worker<T> {
+    jobs: Sender<Job<T>>,
+    results: Receiver<T>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// The function that starts the worker thread with the provider.
+    /// The thread stops when the worker is dropped.
+    pub fn spawn(provider: Box<dyn WeatherProvider>) -> Worker<T> {
+        let (jobs, job_receiver) = mpsc::channel::<Job<T>>();
+        let (result_sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for job in job_receiver {
+                if result_sender.send(job(provider.as_ref())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Worker { jobs, results }
+    }
+
+    /// The function that queues a job to run with the provider on the worker thread.
+    pub fn run(&self, job: impl FnOnce(&dyn WeatherProvider) -> T + Send + 'static) {
+        self.jobs.send(Box::new(job)).ok();
+    }
+
+    /// The function that returns the result of a finished job, without waiting.
+    pub fn try_recv(&self) -> Option<T> {
+        self.results.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::WeatherError;
+    use crate::provider::{Location, Place};
+    use crate::report::{Forecast, WeatherReport};
+    use std::time::Duration;
+
+    struct Offline;
+
+    impl WeatherProvider for Offline {
+        fn name(&self) -> &'static str {
+            "Offline"
+        }
+
+        fn current(&self, _location: &Location) -> Result<WeatherReport, WeatherError> {
+            Err(WeatherError::NotFound)
+        }
+
+        fn forecast(&self, _location: &Location) -> Result<Forecast, WeatherError> {
+            Err(WeatherError::Unsupported("forecasts"))
+        }
+
+        fn geocode(&self, query: &str) -> Result<Vec<Place>, WeatherError> {
+            Ok(vec![Place {
+                name: query.to_string(),
+                lat: 0.0,
+                lon: 0.0,
+                country: String::new(),
+                state: None,
+            }])
+        }
+    }
+
+    fn wait_for<T: Send + 'static>(worker: &Worker<T>) -> T {
+        for _ in 0..200 {
+            if let Some(result) = worker.try_recv() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The worker did not finish the job");
+    }
+
+    #[test]
+    fn test_jobs_run_in_order() {
+        let worker = Worker::spawn(Box::new(Offline));
+
+        worker.run(|provider| provider.geocode("Kyoto").unwrap()[0].name.clone());
+        worker.run(|provider| {
+            provider
+                .current(&Location::City("Nowhere".into()))
+                .is_err()
+                .to_string()
+        });
+
+        assert_eq!(wait_for(&worker), "Kyoto");
+        assert_eq!(wait_for(&worker), "true");
+        assert_eq!(worker.try_recv(), None);
+    }
+}
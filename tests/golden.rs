@@ -0,0 +1,234 @@
+//! Golden image tests for the scenes of every weather condition.
+//!
+//! Every test renders a scene over `Empty.png` with a fixed seed and compares it with the image of the
+//! same name in `tests/goldens`. Small differences between graphics drivers are tolerated: a pixel only
+//! counts as different when its color is clearly off, and a few different pixels are allowed.
+//!
+//! The golden images are recorded, and regenerated after an intended change to the scenes, with
+//! `UPDATE_GOLDENS=1 cargo test --test golden`. Without it a missing golden image fails the test. When a
+//! scene does not match, the rendered image is saved next to the golden image as `<name>.actual.png` for
+//! comparison.
+//!
+//! Machines without any graphics adapter skip the tests, unless `WEATHER_REQUIRE_ADAPTER` is set to say an
+//! adapter is expected, so a broken driver setup cannot pass the tests by skipping them. The Linux CI job
+//! installs Mesa's software Vulkan driver and sets it.
+
+use nannou::draw::Draw;
+use nannou::image::{self, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use weather_visualizer::error::WeatherError;
+use weather_visualizer::render::{self, HeadlessRenderer};
+use weather_visualizer::report::WeatherReport;
+use weather_visualizer::units::Units;
+
+/// The size of the golden images, the size of the window.
+const SIZE: [u32; 2] = [1024, 512];
+
+/// The seed every scene is drawn with.
+const SEED: u64 = 19;
+
+/// The largest perceived color difference, from 0 to 255, for two pixels to count as the same.
+const PIXEL_TOLERANCE: f32 = 24.0;
+
+/// The largest part of the pixels that may differ.
+const MAX_DIFFERENT: f32 = 0.005;
+
+// London on 2023-11-14: the report is at noon, between sunrise at 07:16 and sunset at 16:13 UTC
+const NOON: i64 = 1699962270;
+const MIDNIGHT: i64 = 1700006400;
+const SUNRISE: i64 = 1699946160;
+const SUNSET: i64 = 1699978380;
+
+/// The function that makes a report of the weather condition at the time, without coordinates so the sun
+/// follows its simple arc.
+fn report(id: i64, dt: i64) -> WeatherReport {
+    serde_json::from_str(&format!(
+        r#"{{
+            "weather": [{{"id": {id}, "description": "condition {id}"}}],
+            "main": {{"temp": 12.5}},
+            "wind": {{"speed": 6.0, "deg": 250.0}},
+            "dt": {dt},
+            "sys": {{"sunrise": {SUNRISE}, "sunset": {SUNSET}}},
+            "name": "Golden"
+        }}"#
+    ))
+    .unwrap()
+}
+
+/// The function that returns the part of the pixels that look different, with the perceived difference of
+/// two pixels weighted by how sensitive the eye is to red, green and blue.
+fn difference(actual: &RgbaImage, expected: &RgbaImage) -> f32 {
+    if actual.dimensions() != expected.dimensions() {
+        return 1.0;
+    }
+
+    let distance = |a: &Rgba<u8>, b: &Rgba<u8>| {
+        let d = |i: usize| a[i] as f32 - b[i] as f32;
+        (0.299 * d(0).powi(2) + 0.587 * d(1).powi(2) + 0.114 * d(2).powi(2)).sqrt()
+    };
+    let different = actual
+        .pixels()
+        .zip(expected.pixels())
+        .filter(|(a, b)| distance(a, b) > PIXEL_TOLERANCE)
+        .count();
+
+    different as f32 / (actual.width() * actual.height()) as f32
+}
+
+/// The function that returns whether the machine is expected to have a graphics adapter, so the tests fail
+/// instead of being skipped without one.
+fn adapter_required() -> bool {
+    std::env::var("WEATHER_REQUIRE_ADAPTER").is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The function that renders the scene for the report and compares it with the golden image of the name.
+fn check(name: &str, report: &WeatherReport) {
+    let mut renderer = match HeadlessRenderer::new(SIZE) {
+        Ok(renderer) => renderer,
+        Err(WeatherError::Render(e)) if e.contains("adapter") && !adapter_required() => {
+            eprintln!("Skipping the golden image test '{}': {}", name, e);
+            return;
+        }
+        Err(e) => panic!("{}", e),
+    };
+    let texture = renderer
+        .load_texture(Path::new("src/assets/Empty.png"))
+        .unwrap();
+
+    let draw = Draw::new();
+    render::draw_report(
+        &draw,
+        renderer.rect(),
+        &texture,
+        report,
+        SEED,
+        Units::Metric,
+    );
+    let actual = renderer.render(&draw).unwrap();
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/goldens");
+    let golden = dir.join(format!("{}.png", name));
+    if std::env::var("UPDATE_GOLDENS").is_ok() {
+        std::fs::create_dir_all(&dir).unwrap();
+        actual.save(&golden).unwrap();
+        eprintln!("Recorded the golden image {}", golden.display());
+        return;
+    }
+    assert!(
+        golden.exists(),
+        "The golden image {} is missing, record it with UPDATE_GOLDENS=1 cargo test --test golden",
+        golden.display()
+    );
+
+    let expected = image::open(&golden).unwrap().to_rgba8();
+    let different = difference(&actual, &expected);
+    if different > MAX_DIFFERENT {
+        let actual_path = dir.join(format!("{}.actual.png", name));
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{:.2}% of the pixels of '{}' differ from the golden image, see {}",
+            different * 100.0,
+            name,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn test_difference() {
+    let grey = RgbaImage::from_pixel(10, 10, Rgba([128, 128, 128, 255]));
+    let mut nearly = grey.clone();
+    nearly.put_pixel(0, 0, Rgba([140, 128, 128, 255]));
+    let mut spotted = grey.clone();
+    spotted.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+
+    assert_eq!(difference(&grey, &grey), 0.0);
+    assert_eq!(difference(&grey, &nearly), 0.0);
+    assert_eq!(difference(&grey, &spotted), 0.01);
+    assert_eq!(difference(&grey, &RgbaImage::new(5, 5)), 1.0);
+}
+
+#[test]
+fn test_clear_day() {
+    check("clear_day", &report(800, NOON));
+}
+
+#[test]
+fn test_clear_night() {
+    check("clear_night", &report(800, MIDNIGHT));
+}
+
+#[test]
+fn test_dawn() {
+    check("dawn", &report(800, SUNRISE + 300));
+}
+
+#[test]
+fn test_few_clouds() {
+    check("few_clouds", &report(801, NOON));
+}
+
+#[test]
+fn test_overcast() {
+    check("overcast", &report(804, NOON));
+}
+
+#[test]
+fn test_drizzle() {
+    check("drizzle", &report(301, NOON));
+}
+
+#[test]
+fn test_rain() {
+    check("rain", &report(502, NOON));
+}
+
+#[test]
+fn test_freezing_rain() {
+    check("freezing_rain", &report(511, NOON));
+}
+
+#[test]
+fn test_thunderstorm() {
+    check("thunderstorm", &report(211, NOON));
+}
+
+#[test]
+fn test_snow() {
+    check("snow", &report(601, NOON));
+}
+
+#[test]
+fn test_sleet() {
+    check("sleet", &report(611, NOON));
+}
+
+#[test]
+fn test_mist() {
+    check("mist", &report(701, NOON));
+}
+
+#[test]
+fn test_smoke() {
+    check("smoke", &report(711, NOON));
+}
+
+#[test]
+fn test_dust() {
+    check("dust", &report(731, NOON));
+}
+
+#[test]
+fn test_squall() {
+    check("squall", &report(771, NOON));
+}
+
+#[test]
+fn test_tornado() {
+    check("tornado", &report(781, NOON));
+}
+
+#[test]
+fn test_unknown() {
+    check("unknown", &report(999, NOON));
+}